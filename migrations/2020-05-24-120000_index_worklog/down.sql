@@ -0,0 +1 @@
+DROP INDEX IF EXISTS worklog_task_stopped_ignored;
\ No newline at end of file
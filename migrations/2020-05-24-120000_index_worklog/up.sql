@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS worklog_task_stopped_ignored ON worklog (task_id, stopped, ignored);
\ No newline at end of file
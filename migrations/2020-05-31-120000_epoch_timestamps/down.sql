@@ -0,0 +1,34 @@
+CREATE TABLE task_new (
+    id INTEGER NOT NULL,
+    created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    taskname TEXT NOT NULL UNIQUE,
+    notes TEXT,
+    allocated INTEGER NOT NULL DEFAULT 0,
+    duedate DATETIME,
+    done BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY(id DESC)
+);
+INSERT INTO task_new (id, created, taskname, notes, allocated, duedate, done)
+    SELECT id, datetime(created, 'unixepoch'), taskname, notes, allocated, duedate, done
+    FROM task;
+
+CREATE TABLE worklog_new (
+    id INTEGER NOT NULL,
+    task_id INTEGER NOT NULL,
+    started DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    stopped DATETIME NULL DEFAULT NULL,
+    duration INTEGER NOT NULL DEFAULT 0,
+    ignored BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY(id DESC),
+    FOREIGN KEY(task_id) REFERENCES task(id)
+);
+INSERT INTO worklog_new (id, task_id, started, stopped, duration, ignored)
+    SELECT id, task_id, datetime(started, 'unixepoch'), datetime(stopped, 'unixepoch'),
+        duration, ignored
+    FROM worklog;
+
+DROP TABLE worklog;
+DROP TABLE task;
+ALTER TABLE task_new RENAME TO task;
+ALTER TABLE worklog_new RENAME TO worklog;
+CREATE INDEX worklog_task_stopped_ignored ON worklog (task_id, stopped, ignored);
\ No newline at end of file
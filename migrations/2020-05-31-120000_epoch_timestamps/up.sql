@@ -0,0 +1,34 @@
+CREATE TABLE task_new (
+    id INTEGER NOT NULL,
+    created INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    taskname TEXT NOT NULL UNIQUE,
+    notes TEXT,
+    allocated INTEGER NOT NULL DEFAULT 0,
+    duedate DATETIME,
+    done BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY(id DESC)
+);
+INSERT INTO task_new (id, created, taskname, notes, allocated, duedate, done)
+    SELECT id, CAST(strftime('%s', created) AS INTEGER), taskname, notes, allocated, duedate, done
+    FROM task;
+
+CREATE TABLE worklog_new (
+    id INTEGER NOT NULL,
+    task_id INTEGER NOT NULL,
+    started INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    stopped INTEGER NULL DEFAULT NULL,
+    duration INTEGER NOT NULL DEFAULT 0,
+    ignored BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY(id DESC),
+    FOREIGN KEY(task_id) REFERENCES task(id)
+);
+INSERT INTO worklog_new (id, task_id, started, stopped, duration, ignored)
+    SELECT id, task_id, CAST(strftime('%s', started) AS INTEGER),
+        CAST(strftime('%s', stopped) AS INTEGER), duration, ignored
+    FROM worklog;
+
+DROP TABLE worklog;
+DROP TABLE task;
+ALTER TABLE task_new RENAME TO task;
+ALTER TABLE worklog_new RENAME TO worklog;
+CREATE INDEX worklog_task_stopped_ignored ON worklog (task_id, stopped, ignored);
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS tag (
+    id INTEGER NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    PRIMARY KEY(id DESC)
+);
+CREATE TABLE IF NOT EXISTS task_tag (
+    task_id INTEGER NOT NULL,
+    tag_id INTEGER NOT NULL,
+    PRIMARY KEY(task_id, tag_id),
+    FOREIGN KEY(task_id) REFERENCES task(id),
+    FOREIGN KEY(tag_id) REFERENCES tag(id)
+);
+CREATE INDEX IF NOT EXISTS task_tag_tag ON task_tag (tag_id);
\ No newline at end of file
@@ -0,0 +1,16 @@
+CREATE TABLE worklog_new (
+    id INTEGER NOT NULL,
+    task_id INTEGER NOT NULL,
+    started INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    stopped INTEGER NULL DEFAULT NULL,
+    duration INTEGER NOT NULL DEFAULT 0,
+    ignored BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY(id DESC),
+    FOREIGN KEY(task_id) REFERENCES task(id)
+);
+INSERT INTO worklog_new (id, task_id, started, stopped, duration, ignored)
+    SELECT id, task_id, started, stopped, duration, ignored FROM worklog;
+
+DROP TABLE worklog;
+ALTER TABLE worklog_new RENAME TO worklog;
+CREATE INDEX worklog_task_stopped_ignored ON worklog (task_id, stopped, ignored);
\ No newline at end of file
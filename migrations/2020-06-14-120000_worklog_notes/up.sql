@@ -0,0 +1 @@
+ALTER TABLE worklog ADD COLUMN notes TEXT;
\ No newline at end of file
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS pause (
+    id INTEGER NOT NULL,
+    worklog_id INTEGER NOT NULL,
+    started INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    stopped INTEGER NULL DEFAULT NULL,
+    PRIMARY KEY(id DESC),
+    FOREIGN KEY(worklog_id) REFERENCES worklog(id)
+);
+CREATE INDEX IF NOT EXISTS pause_worklog ON pause (worklog_id);
\ No newline at end of file
@@ -0,0 +1,15 @@
+CREATE TABLE task_new (
+    id INTEGER NOT NULL,
+    created INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    taskname TEXT NOT NULL UNIQUE,
+    notes TEXT,
+    allocated INTEGER NOT NULL DEFAULT 0,
+    duedate DATETIME,
+    done BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY(id DESC)
+);
+INSERT INTO task_new (id, created, taskname, notes, allocated, duedate, done)
+    SELECT id, created, taskname, notes, allocated, duedate, done FROM task;
+
+DROP TABLE task;
+ALTER TABLE task_new RENAME TO task;
\ No newline at end of file
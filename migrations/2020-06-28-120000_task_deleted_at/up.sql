@@ -0,0 +1 @@
+ALTER TABLE task ADD COLUMN deleted_at INTEGER NULL DEFAULT NULL;
\ No newline at end of file
@@ -0,0 +1,17 @@
+DROP TRIGGER IF EXISTS undo_task_insert;
+DROP TRIGGER IF EXISTS undo_task_update;
+DROP TRIGGER IF EXISTS undo_task_delete;
+DROP TRIGGER IF EXISTS undo_worklog_insert;
+DROP TRIGGER IF EXISTS undo_worklog_update;
+DROP TRIGGER IF EXISTS undo_worklog_delete;
+DROP TRIGGER IF EXISTS undo_pause_insert;
+DROP TRIGGER IF EXISTS undo_pause_update;
+DROP TRIGGER IF EXISTS undo_pause_delete;
+DROP TRIGGER IF EXISTS undo_tag_insert;
+DROP TRIGGER IF EXISTS undo_tag_update;
+DROP TRIGGER IF EXISTS undo_tag_delete;
+DROP TRIGGER IF EXISTS undo_task_tag_insert;
+DROP TRIGGER IF EXISTS undo_task_tag_update;
+DROP TRIGGER IF EXISTS undo_task_tag_delete;
+DROP TABLE IF EXISTS undo_log;
+DROP TABLE IF EXISTS undo_op;
\ No newline at end of file
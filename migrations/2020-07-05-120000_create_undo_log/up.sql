@@ -0,0 +1,270 @@
+-- every mutation made while an operation is open is logged with the row
+-- images before and after it, NULL for inserted and deleted rows
+CREATE TABLE undo_op (
+    id INTEGER NOT NULL,
+    created INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    command TEXT NOT NULL,
+    open BOOLEAN NOT NULL DEFAULT 1,
+    PRIMARY KEY(id)
+);
+CREATE TABLE undo_log (
+    id INTEGER NOT NULL,
+    op_id INTEGER NOT NULL,
+    tablename TEXT NOT NULL,
+    before TEXT NULL,
+    after TEXT NULL,
+    PRIMARY KEY(id),
+    FOREIGN KEY(op_id) REFERENCES undo_op(id)
+);
+CREATE INDEX undo_log_op ON undo_log (op_id);
+
+CREATE TRIGGER undo_task_insert AFTER INSERT ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        NULL,
+        json_object(
+            'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+            'notes', NEW.notes, 'allocated', NEW.allocated,
+            'duedate', NEW.duedate, 'done', NEW.done,
+            'deleted_at', NEW.deleted_at
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_update AFTER UPDATE ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+        'notes', OLD.notes, 'allocated', OLD.allocated,
+        'duedate', OLD.duedate, 'done', OLD.done,
+        'deleted_at', OLD.deleted_at
+    ) IS NOT json_object(
+        'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+        'notes', NEW.notes, 'allocated', NEW.allocated,
+        'duedate', NEW.duedate, 'done', NEW.done,
+        'deleted_at', NEW.deleted_at
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        json_object(
+            'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+            'notes', OLD.notes, 'allocated', OLD.allocated,
+            'duedate', OLD.duedate, 'done', OLD.done,
+            'deleted_at', OLD.deleted_at
+        ),
+        json_object(
+            'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+            'notes', NEW.notes, 'allocated', NEW.allocated,
+            'duedate', NEW.duedate, 'done', NEW.done,
+            'deleted_at', NEW.deleted_at
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_delete AFTER DELETE ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        json_object(
+            'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+            'notes', OLD.notes, 'allocated', OLD.allocated,
+            'duedate', OLD.duedate, 'done', OLD.done,
+            'deleted_at', OLD.deleted_at
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_worklog_insert AFTER INSERT ON worklog
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'worklog',
+        NULL,
+        json_object(
+            'id', NEW.id, 'task_id', NEW.task_id, 'started', NEW.started,
+            'stopped', NEW.stopped, 'duration', NEW.duration,
+            'ignored', NEW.ignored, 'notes', NEW.notes
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_worklog_update AFTER UPDATE ON worklog
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'id', OLD.id, 'task_id', OLD.task_id, 'started', OLD.started,
+        'stopped', OLD.stopped, 'duration', OLD.duration,
+        'ignored', OLD.ignored, 'notes', OLD.notes
+    ) IS NOT json_object(
+        'id', NEW.id, 'task_id', NEW.task_id, 'started', NEW.started,
+        'stopped', NEW.stopped, 'duration', NEW.duration,
+        'ignored', NEW.ignored, 'notes', NEW.notes
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'worklog',
+        json_object(
+            'id', OLD.id, 'task_id', OLD.task_id, 'started', OLD.started,
+            'stopped', OLD.stopped, 'duration', OLD.duration,
+            'ignored', OLD.ignored, 'notes', OLD.notes
+        ),
+        json_object(
+            'id', NEW.id, 'task_id', NEW.task_id, 'started', NEW.started,
+            'stopped', NEW.stopped, 'duration', NEW.duration,
+            'ignored', NEW.ignored, 'notes', NEW.notes
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_worklog_delete AFTER DELETE ON worklog
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'worklog',
+        json_object(
+            'id', OLD.id, 'task_id', OLD.task_id, 'started', OLD.started,
+            'stopped', OLD.stopped, 'duration', OLD.duration,
+            'ignored', OLD.ignored, 'notes', OLD.notes
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_pause_insert AFTER INSERT ON pause
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'pause',
+        NULL,
+        json_object(
+            'id', NEW.id, 'worklog_id', NEW.worklog_id,
+            'started', NEW.started, 'stopped', NEW.stopped
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_pause_update AFTER UPDATE ON pause
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'id', OLD.id, 'worklog_id', OLD.worklog_id, 'started', OLD.started,
+        'stopped', OLD.stopped
+    ) IS NOT json_object(
+        'id', NEW.id, 'worklog_id', NEW.worklog_id, 'started', NEW.started,
+        'stopped', NEW.stopped
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'pause',
+        json_object(
+            'id', OLD.id, 'worklog_id', OLD.worklog_id,
+            'started', OLD.started, 'stopped', OLD.stopped
+        ),
+        json_object(
+            'id', NEW.id, 'worklog_id', NEW.worklog_id,
+            'started', NEW.started, 'stopped', NEW.stopped
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_pause_delete AFTER DELETE ON pause
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'pause',
+        json_object(
+            'id', OLD.id, 'worklog_id', OLD.worklog_id,
+            'started', OLD.started, 'stopped', OLD.stopped
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_tag_insert AFTER INSERT ON tag
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'tag',
+        NULL,
+        json_object(
+            'id', NEW.id, 'name', NEW.name
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_tag_update AFTER UPDATE ON tag
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'id', OLD.id, 'name', OLD.name
+    ) IS NOT json_object(
+        'id', NEW.id, 'name', NEW.name
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'tag',
+        json_object(
+            'id', OLD.id, 'name', OLD.name
+        ),
+        json_object(
+            'id', NEW.id, 'name', NEW.name
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_tag_delete AFTER DELETE ON tag
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'tag',
+        json_object(
+            'id', OLD.id, 'name', OLD.name
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_tag_insert AFTER INSERT ON task_tag
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task_tag',
+        NULL,
+        json_object(
+            'task_id', NEW.task_id, 'tag_id', NEW.tag_id
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_tag_update AFTER UPDATE ON task_tag
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'task_id', OLD.task_id, 'tag_id', OLD.tag_id
+    ) IS NOT json_object(
+        'task_id', NEW.task_id, 'tag_id', NEW.tag_id
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task_tag',
+        json_object(
+            'task_id', OLD.task_id, 'tag_id', OLD.tag_id
+        ),
+        json_object(
+            'task_id', NEW.task_id, 'tag_id', NEW.tag_id
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_tag_delete AFTER DELETE ON task_tag
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task_tag',
+        json_object(
+            'task_id', OLD.task_id, 'tag_id', OLD.tag_id
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
\ No newline at end of file
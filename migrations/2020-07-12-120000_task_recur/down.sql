@@ -0,0 +1,81 @@
+DROP TRIGGER IF EXISTS undo_task_insert;
+DROP TRIGGER IF EXISTS undo_task_update;
+DROP TRIGGER IF EXISTS undo_task_delete;
+
+CREATE TABLE task_new (
+    id INTEGER NOT NULL,
+    created INTEGER NOT NULL DEFAULT (CAST(strftime('%s', 'now') AS INTEGER)),
+    taskname TEXT NOT NULL UNIQUE,
+    notes TEXT,
+    allocated INTEGER NOT NULL DEFAULT 0,
+    duedate DATETIME,
+    done BOOLEAN NOT NULL DEFAULT 0,
+    deleted_at INTEGER NULL DEFAULT NULL,
+    PRIMARY KEY(id DESC)
+);
+INSERT INTO task_new (id, created, taskname, notes, allocated, duedate, done, deleted_at)
+    SELECT id, created, taskname, notes, allocated, duedate, done, deleted_at FROM task;
+
+DROP TABLE task;
+ALTER TABLE task_new RENAME TO task;
+
+CREATE TRIGGER undo_task_insert AFTER INSERT ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        NULL,
+        json_object(
+            'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+            'notes', NEW.notes, 'allocated', NEW.allocated,
+            'duedate', NEW.duedate, 'done', NEW.done,
+            'deleted_at', NEW.deleted_at
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_update AFTER UPDATE ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+        'notes', OLD.notes, 'allocated', OLD.allocated,
+        'duedate', OLD.duedate, 'done', OLD.done,
+        'deleted_at', OLD.deleted_at
+    ) IS NOT json_object(
+        'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+        'notes', NEW.notes, 'allocated', NEW.allocated,
+        'duedate', NEW.duedate, 'done', NEW.done,
+        'deleted_at', NEW.deleted_at
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        json_object(
+            'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+            'notes', OLD.notes, 'allocated', OLD.allocated,
+            'duedate', OLD.duedate, 'done', OLD.done,
+            'deleted_at', OLD.deleted_at
+        ),
+        json_object(
+            'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+            'notes', NEW.notes, 'allocated', NEW.allocated,
+            'duedate', NEW.duedate, 'done', NEW.done,
+            'deleted_at', NEW.deleted_at
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_delete AFTER DELETE ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        json_object(
+            'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+            'notes', OLD.notes, 'allocated', OLD.allocated,
+            'duedate', OLD.duedate, 'done', OLD.done,
+            'deleted_at', OLD.deleted_at
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
@@ -0,0 +1,68 @@
+-- recurrence rule of the task, e.g. `weekly:mon,thu`, see `recurrence.rs`
+ALTER TABLE task ADD COLUMN recur TEXT NULL DEFAULT NULL;
+
+-- the undo triggers list the task columns explicitly
+DROP TRIGGER IF EXISTS undo_task_insert;
+DROP TRIGGER IF EXISTS undo_task_update;
+DROP TRIGGER IF EXISTS undo_task_delete;
+
+CREATE TRIGGER undo_task_insert AFTER INSERT ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        NULL,
+        json_object(
+            'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+            'notes', NEW.notes, 'allocated', NEW.allocated,
+            'duedate', NEW.duedate, 'done', NEW.done,
+            'deleted_at', NEW.deleted_at, 'recur', NEW.recur
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_update AFTER UPDATE ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+    AND json_object(
+        'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+        'notes', OLD.notes, 'allocated', OLD.allocated,
+        'duedate', OLD.duedate, 'done', OLD.done,
+        'deleted_at', OLD.deleted_at, 'recur', OLD.recur
+    ) IS NOT json_object(
+        'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+        'notes', NEW.notes, 'allocated', NEW.allocated,
+        'duedate', NEW.duedate, 'done', NEW.done,
+        'deleted_at', NEW.deleted_at, 'recur', NEW.recur
+    )
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        json_object(
+            'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+            'notes', OLD.notes, 'allocated', OLD.allocated,
+            'duedate', OLD.duedate, 'done', OLD.done,
+            'deleted_at', OLD.deleted_at, 'recur', OLD.recur
+        ),
+        json_object(
+            'id', NEW.id, 'created', NEW.created, 'taskname', NEW.taskname,
+            'notes', NEW.notes, 'allocated', NEW.allocated,
+            'duedate', NEW.duedate, 'done', NEW.done,
+            'deleted_at', NEW.deleted_at, 'recur', NEW.recur
+        )
+    FROM undo_op WHERE open = 1;
+END;
+
+CREATE TRIGGER undo_task_delete AFTER DELETE ON task
+WHEN EXISTS (SELECT 1 FROM undo_op WHERE open = 1)
+BEGIN
+    INSERT INTO undo_log (op_id, tablename, before, after)
+    SELECT MAX(id), 'task',
+        json_object(
+            'id', OLD.id, 'created', OLD.created, 'taskname', OLD.taskname,
+            'notes', OLD.notes, 'allocated', OLD.allocated,
+            'duedate', OLD.duedate, 'done', OLD.done,
+            'deleted_at', OLD.deleted_at, 'recur', OLD.recur
+        ),
+        NULL
+    FROM undo_op WHERE open = 1;
+END;
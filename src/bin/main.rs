@@ -3,9 +3,12 @@ use ttrackr::*;
 use dotenv::dotenv;
 use log::debug;
 
-fn main() -> Result<(), utils::BoxError> {
+fn main() {
     dotenv().ok();
     env_logger::init();
     debug!("test");
-    cli::parse_cli()
+    if let Err(err) = cli::parse_cli() {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
@@ -1,17 +1,35 @@
 // cli args parser
+use crate::completions;
 use crate::config;
-use crate::db::ops;
-use crate::db::utils::TaskNotFound;
-use crate::utils::{fmt_duration, open_naivedate, unwrap_string, utc_to_local_naive, BoxError};
+use crate::db::archive::Archive;
+use crate::db::ops::{MergeAllocation, MergeNotes, RunningTask, Tracker, WorklogEvent};
+use crate::db::{calendar, importers};
+use crate::error::Error;
+use crate::output::{print_records, OutputFormat};
+use crate::recurrence::Recurrence;
+use crate::utils::{
+    fmt_compact_duration, fmt_duration, get_timestamp, local_to_utc, open_naivedate,
+    parse_duration, parse_local_time, seconds_between, utc_to_iso8601, utc_to_local_naive,
+    BoxError,
+};
 
-use chrono::NaiveDate;
+use ansi_term::Style;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
 use comfy_table::Table;
-use dialoguer::Confirm;
+use dialoguer::{Confirm, Input, Select};
 use log::debug;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::IsTerminal;
+use std::io::{self, BufReader};
 use std::path::PathBuf;
-use structopt::clap::AppSettings;
+use structopt::clap::{AppSettings, Shell};
 use structopt::StructOpt;
 
+/// Shells `completions` can generate scripts for.
+const COMPLETION_SHELLS: [&str; 4] = ["bash", "zsh", "fish", "elvish"];
+
 #[derive(StructOpt, Debug)]
 // #[structopt(setting = AppSettings::InferSubcommands)]
 struct Cli {
@@ -19,6 +37,15 @@ struct Cli {
     config: Option<PathBuf>,
     #[structopt(parse(from_os_str), help = "database file", long)]
     dbfile: Option<PathBuf>,
+    #[structopt(
+        short = "o",
+        long = "output",
+        help = "Output format of listings",
+        possible_values = &["table", "json", "csv", "tsv"],
+        default_value = "table",
+        global = true
+    )]
+    output: OutputFormat,
     #[structopt(subcommand)]
     cmd: Sub,
 }
@@ -31,23 +58,102 @@ enum Sub {
     Edit(EditOpts),
     #[structopt(name = "delete", visible_alias = "del")]
     Delete(DeleteOpts),
+    #[structopt(name = "rename", visible_alias = "mv")]
+    Rename(RenameOpts),
+    #[structopt(name = "merge")]
+    Merge(MergeOpts),
+    #[structopt(name = "trash")]
+    Trash(TrashOpts),
+    #[structopt(name = "recur")]
+    Recur(RecurOpts),
+    #[structopt(name = "undo")]
+    Undo(UndoOpts),
+    #[structopt(name = "history")]
+    History(HistoryOpts),
     #[structopt(name = "start")]
     Start(StartOpts),
     #[structopt(name = "stop")]
     Stop(StopOpts),
     #[structopt(name = "stopall")]
     StopAll(StopAllOpts),
+    #[structopt(name = "switch", visible_alias = "sw")]
+    Switch(SwitchOpts),
+    #[structopt(name = "pause")]
+    Pause(PauseOpts),
+    #[structopt(name = "resume")]
+    Resume(ResumeOpts),
+    #[structopt(name = "log", visible_alias = "add")]
+    Log(LogOpts),
+    #[structopt(name = "worklog", visible_alias = "wl")]
+    Worklog(WorklogOpts),
     #[structopt(name = "status", visible_alias = "stat")]
     Status(StatusOpts),
     #[structopt(name = "list", visible_alias = "ls")]
     List(ListOpts),
+    #[structopt(name = "report")]
+    Report(ReportOpts),
+    #[structopt(name = "tags")]
+    Tags(TagsOpts),
+    #[structopt(name = "prompt")]
+    Prompt(PromptOpts),
+    #[structopt(name = "export")]
+    Export(ExportOpts),
+    #[structopt(name = "import")]
+    Import(ImportOpts),
+    #[structopt(name = "completions")]
+    Completions(CompletionsOpts),
     #[structopt(name = "test", setting = AppSettings::Hidden)]
     Test(TestOpts),
 }
 
+impl Sub {
+    /// Whether the command may change data, such commands are recorded in the
+    /// undo log.
+    fn changes_data(&self) -> bool {
+        match self {
+            Sub::List(_)
+            | Sub::Status(_)
+            | Sub::Report(_)
+            | Sub::Tags(_)
+            | Sub::Prompt(_)
+            | Sub::Export(_)
+            | Sub::Completions(_)
+            | Sub::Undo(_)
+            | Sub::History(_)
+            | Sub::Test(_) => false,
+            Sub::Worklog(args) => !matches!(args.cmd, WorklogSub::List(_)),
+            Sub::Trash(args) => !matches!(args.cmd, TrashSub::List),
+            Sub::Recur(args) => !matches!(args.cmd, RecurSub::List),
+            _ => true,
+        }
+    }
+}
+
 #[derive(StructOpt, Debug)]
 struct TestOpts {}
 
+#[derive(StructOpt, Debug)]
+struct CompletionsOpts {
+    #[structopt(help = "Shell to complete in", possible_values = &COMPLETION_SHELLS)]
+    shell: Shell,
+}
+
+/// Arguments of the hidden `__complete-tasks` helper called by completion scripts.
+///
+/// Parsed apart from `Cli` so the generated scripts do not offer it.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "__complete-tasks")]
+struct CompleteTasksOpts {
+    #[structopt(parse(from_os_str), help = "config file", long)]
+    config: Option<PathBuf>,
+    #[structopt(parse(from_os_str), help = "database file", long)]
+    dbfile: Option<PathBuf>,
+    #[structopt(long, help = "Only running tasks", conflicts_with = "incomplete")]
+    running: bool,
+    #[structopt(long, help = "Only incomplete tasks")]
+    incomplete: bool,
+}
+
 #[derive(StructOpt, Debug)]
 struct CreateOpts {
     #[structopt(help = "New task name")]
@@ -58,6 +164,8 @@ struct CreateOpts {
     duedate: Option<NaiveDate>,
     #[structopt(short = "n", long = "note", help = "Description")]
     note: Option<String>,
+    #[structopt(long = "tag", number_of_values = 1, help = "Add tag, can be repeated")]
+    tags: Vec<String>,
 }
 
 #[derive(StructOpt, Debug)]
@@ -74,6 +182,14 @@ struct EditOpts {
     finish: bool,
     #[structopt(short, long, help = "Set as incomplete")]
     incomplete: bool,
+    #[structopt(long = "tag", number_of_values = 1, help = "Add tag, can be repeated")]
+    tags: Vec<String>,
+    #[structopt(
+        long = "untag",
+        number_of_values = 1,
+        help = "Remove tag, can be repeated"
+    )]
+    untags: Vec<String>,
 }
 
 #[derive(StructOpt, Debug)]
@@ -84,25 +200,338 @@ struct DeleteOpts {
     noconfirm: bool,
 }
 
+#[derive(StructOpt, Debug)]
+struct RenameOpts {
+    #[structopt(help = "Task name")]
+    old: String,
+    #[structopt(help = "New task name")]
+    new: String,
+    #[structopt(short, long, help = "Also rename subtasks old::* to new::*")]
+    recursive: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct MergeOpts {
+    #[structopt(help = "Task name, deleted after the merge")]
+    source: String,
+    #[structopt(help = "Task name receiving the worklogs")]
+    target: String,
+    #[structopt(
+        long,
+        possible_values = &["keep", "source", "append"],
+        default_value = "append",
+        help = "Notes to keep: of the target, of the source or both"
+    )]
+    notes: MergeNotes,
+    #[structopt(
+        long,
+        possible_values = &["keep", "source", "sum", "max"],
+        default_value = "sum",
+        help = "Allocated time to keep"
+    )]
+    allocated: MergeAllocation,
+    #[structopt(long, help = "Stop the source when both tasks are running")]
+    force: bool,
+    #[structopt(long, help = "Skip confirmation")]
+    noconfirm: bool,
+}
+
 #[derive(StructOpt, Debug)]
 struct StartOpts {
     #[structopt(help = "Task name(s)", required = true)]
     name: Vec<String>,
+    #[structopt(flatten)]
+    time: TimeOpts,
+    #[structopt(
+        short = "m",
+        long = "message",
+        help = "Note on what the session is about"
+    )]
+    message: Option<String>,
 }
 
 #[derive(StructOpt, Debug)]
 struct StopOpts {
     #[structopt(help = "Task name(s)", required = true)]
     name: Vec<String>,
+    #[structopt(flatten)]
+    time: TimeOpts,
+    #[structopt(
+        short = "m",
+        long = "message",
+        help = "Note on what was done, appended to the session's notes"
+    )]
+    message: Option<String>,
+    #[structopt(
+        long,
+        help = "End sessions longer than max_session at the threshold without asking"
+    )]
+    truncate: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct StopAllOpts {
+    #[structopt(flatten)]
+    time: TimeOpts,
+    #[structopt(
+        long,
+        help = "End sessions longer than max_session at the threshold without asking"
+    )]
+    truncate: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct PromptOpts {
+    #[structopt(
+        short = "f",
+        long = "format",
+        default_value = "{task} {elapsed}",
+        help = "Placeholders: {task} and {elapsed} of the last started task, its {total} \
+            spent time and {count} of running tasks"
+    )]
+    format: String,
+}
+
+#[derive(StructOpt, Debug)]
+struct SwitchOpts {
+    #[structopt(help = "Task name")]
+    name: String,
+    #[structopt(flatten)]
+    time: TimeOpts,
+    #[structopt(
+        short = "m",
+        long = "message",
+        help = "Note on what the session is about"
+    )]
+    message: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+struct PauseOpts {
+    #[structopt(help = "Task name(s)", required = true)]
+    name: Vec<String>,
+    #[structopt(flatten)]
+    time: TimeOpts,
+}
+
+#[derive(StructOpt, Debug)]
+struct ResumeOpts {
+    #[structopt(help = "Task name(s)", required = true)]
+    name: Vec<String>,
+    #[structopt(flatten)]
+    time: TimeOpts,
+}
+
+#[derive(StructOpt, Debug)]
+struct TimeOpts {
+    #[structopt(
+        long = "at",
+        help = "Use this local time instead of now, e.g. \"2020-05-10 13:00\" or \"13:00\"",
+        conflicts_with = "ago"
+    )]
+    at: Option<String>,
+    #[structopt(
+        long = "ago",
+        help = "Use a time this long before now, e.g. 20m or 1h30m",
+        parse(try_from_str = parse_duration)
+    )]
+    ago: Option<i32>,
+}
+
+impl TimeOpts {
+    /// Resolve given options into a UTC time, `None` means now.
+    fn to_utc(&self) -> Result<Option<DateTime<Utc>>, BoxError> {
+        if let Some(at) = &self.at {
+            return Ok(Some(local_to_utc(&parse_local_time(at)?)?));
+        }
+        if let Some(seconds) = self.ago {
+            return Ok(Some(
+                get_timestamp() - Duration::seconds(i64::from(seconds)),
+            ));
+        }
+        Ok(None)
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct LogOpts {
+    #[structopt(help = "Task name")]
+    name: String,
+    #[structopt(
+        short = "s",
+        long = "start",
+        help = "Start time in local time, e.g. \"2020-05-10 13:00\" or \"13:00\""
+    )]
+    start: String,
+    #[structopt(
+        short = "e",
+        long = "end",
+        help = "End time in local time",
+        required_unless = "duration",
+        conflicts_with = "duration"
+    )]
+    end: Option<String>,
+    #[structopt(
+        short = "d",
+        long = "duration",
+        help = "Duration, e.g. 1h30m, 45m or 90s",
+        parse(try_from_str = parse_duration)
+    )]
+    duration: Option<i32>,
+}
+
+#[derive(StructOpt, Debug)]
+struct WorklogOpts {
+    #[structopt(subcommand)]
+    cmd: WorklogSub,
+}
+
+#[derive(StructOpt, Debug)]
+enum WorklogSub {
+    #[structopt(name = "list", visible_alias = "ls")]
+    List(WorklogListOpts),
+    #[structopt(name = "edit")]
+    Edit(WorklogEditOpts),
+    #[structopt(name = "delete", visible_alias = "del")]
+    Delete(WorklogDeleteOpts),
+    #[structopt(name = "ignore")]
+    Ignore(WorklogIdOpts),
+    #[structopt(name = "unignore")]
+    Unignore(WorklogIdOpts),
+}
+
+#[derive(StructOpt, Debug)]
+struct WorklogListOpts {
+    #[structopt(short = "f", long = "filter", name = "task name")]
+    filter: Option<String>,
+    #[structopt(short = "a", long = "all", help = "Include ignored worklogs")]
+    all: bool,
+    #[structopt(short = "n", long = "limit", help = "Show only the latest entries")]
+    limit: Option<i64>,
+}
+
+#[derive(StructOpt, Debug)]
+struct WorklogEditOpts {
+    #[structopt(help = "Worklog id")]
+    id: i32,
+    #[structopt(
+        short = "s",
+        long = "start",
+        help = "Start time in local time",
+        required_unless = "end"
+    )]
+    start: Option<String>,
+    #[structopt(short = "e", long = "end", help = "End time in local time")]
+    end: Option<String>,
+}
+
+#[derive(StructOpt, Debug)]
+struct WorklogDeleteOpts {
+    #[structopt(help = "Worklog id")]
+    id: i32,
+    #[structopt(long, help = "Skip confirmation")]
+    noconfirm: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct TrashOpts {
+    #[structopt(subcommand)]
+    cmd: TrashSub,
+}
+
+#[derive(StructOpt, Debug)]
+enum TrashSub {
+    #[structopt(name = "list", visible_alias = "ls")]
+    List,
+    #[structopt(name = "restore")]
+    Restore(TrashRestoreOpts),
+    #[structopt(name = "purge")]
+    Purge(TrashPurgeOpts),
+}
+
+#[derive(StructOpt, Debug)]
+struct TrashRestoreOpts {
+    #[structopt(help = "Task name")]
+    name: String,
+}
+
+#[derive(StructOpt, Debug)]
+struct TrashPurgeOpts {
+    #[structopt(
+        long = "older-than",
+        help = "Only tasks deleted longer ago than this, e.g. 30d",
+        parse(try_from_str = parse_duration)
+    )]
+    older_than: Option<i32>,
+    #[structopt(long, help = "Skip confirmation")]
+    noconfirm: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct RecurOpts {
+    #[structopt(subcommand)]
+    cmd: RecurSub,
+}
+
+#[derive(StructOpt, Debug)]
+enum RecurSub {
+    #[structopt(name = "list", visible_alias = "ls")]
+    List,
+    #[structopt(name = "set")]
+    Set(RecurSetOpts),
+    #[structopt(name = "clear")]
+    Clear(RecurClearOpts),
+}
+
+#[derive(StructOpt, Debug)]
+struct RecurSetOpts {
+    #[structopt(help = "Task name")]
+    name: String,
+    #[structopt(help = "Rule: daily, weekdays, weekly[:mon,thu,...] or monthly[:day]")]
+    rule: Recurrence,
+}
+
+#[derive(StructOpt, Debug)]
+struct RecurClearOpts {
+    #[structopt(help = "Task name")]
+    name: String,
+}
+
+#[derive(StructOpt, Debug)]
+struct UndoOpts {
+    #[structopt(default_value = "1", help = "Number of commands to undo")]
+    count: i64,
+    #[structopt(long, help = "Skip confirmation")]
+    noconfirm: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct HistoryOpts {
+    #[structopt(
+        short = "n",
+        long = "limit",
+        default_value = "10",
+        help = "Show only the latest commands"
+    )]
+    limit: i64,
 }
 
 #[derive(StructOpt, Debug)]
-struct StopAllOpts {}
+struct WorklogIdOpts {
+    #[structopt(help = "Worklog id")]
+    id: i32,
+}
 
 #[derive(StructOpt, Debug)]
 struct StatusOpts {
     #[structopt(short = "f", long = "filter", name = "task name")]
     filter: Option<String>,
+    #[structopt(
+        long = "tag",
+        number_of_values = 1,
+        help = "Only tasks with this tag, can be repeated"
+    )]
+    tags: Vec<String>,
 }
 
 #[derive(StructOpt, Debug)]
@@ -111,6 +540,111 @@ struct ListOpts {
     status: String,
     #[structopt(short = "f", long = "filter", name = "task name")]
     filter: Option<String>,
+    #[structopt(long = "tree", help = "Show subtasks as a tree, rolling up their time")]
+    tree: bool,
+    #[structopt(
+        long = "tag",
+        number_of_values = 1,
+        help = "Only tasks with this tag, can be repeated"
+    )]
+    tags: Vec<String>,
+}
+
+#[derive(StructOpt, Debug)]
+struct ReportOpts {
+    #[structopt(long, help = "Report for today (default)")]
+    today: bool,
+    #[structopt(
+        long,
+        help = "Report for current week",
+        conflicts_with_all = &["today", "month", "from"]
+    )]
+    week: bool,
+    #[structopt(
+        long,
+        help = "Report for current month",
+        conflicts_with_all = &["today", "from"]
+    )]
+    month: bool,
+    #[structopt(long, help = "First date of the report", conflicts_with = "today")]
+    from: Option<NaiveDate>,
+    #[structopt(
+        long,
+        help = "Last date of the report, defaults to today",
+        requires = "from"
+    )]
+    to: Option<NaiveDate>,
+    #[structopt(short = "f", long = "filter", name = "task name")]
+    filter: Option<String>,
+    #[structopt(
+        long = "tag",
+        number_of_values = 1,
+        help = "Only tasks with this tag, can be repeated"
+    )]
+    tags: Vec<String>,
+}
+
+impl ReportOpts {
+    /// First and last local date of the requested period.
+    fn period(&self) -> (NaiveDate, NaiveDate) {
+        let today = Local::today().naive_local();
+        if self.today {
+            (today, today)
+        } else if self.week {
+            let first = today - Duration::days(i64::from(today.weekday().num_days_from_monday()));
+            (first, first + Duration::days(6))
+        } else if self.month {
+            let first = NaiveDate::from_ymd(today.year(), today.month(), 1);
+            let next = if today.month() == 12 {
+                NaiveDate::from_ymd(today.year() + 1, 1, 1)
+            } else {
+                NaiveDate::from_ymd(today.year(), today.month() + 1, 1)
+            };
+            (first, next.pred())
+        } else if let Some(from) = self.from {
+            (from, self.to.unwrap_or(today))
+        } else {
+            (today, today)
+        }
+    }
+}
+
+#[derive(StructOpt, Debug)]
+struct TagsOpts {}
+
+#[derive(StructOpt, Debug)]
+struct ExportOpts {
+    #[structopt(parse(from_os_str), help = "Output file, defaults to stdout")]
+    file: Option<PathBuf>,
+    #[structopt(
+        long,
+        possible_values = &["json", "ics"],
+        default_value = "json",
+        help = "JSON archive or iCalendar with an event per worklog"
+    )]
+    format: String,
+    #[structopt(long, help = "Add due dates of tasks as to-dos to iCalendar export")]
+    todos: bool,
+}
+
+#[derive(StructOpt, Debug)]
+struct ImportOpts {
+    #[structopt(parse(from_os_str), help = "Archive file created by export")]
+    file: PathBuf,
+    #[structopt(
+        long,
+        possible_values = &["ttrackr", "timewarrior", "toggl"],
+        default_value = "ttrackr",
+        help = "Format of the file: ttrackr archive, `timew export` JSON or Toggl detailed CSV"
+    )]
+    from: String,
+    #[structopt(long, help = "Merge into existing tasks with the same name")]
+    merge: bool,
+    #[structopt(
+        long,
+        help = "Print what would be imported without changing the database"
+    )]
+    dry_run: bool,
 }
 
 enum TaskStatus {
@@ -132,11 +666,135 @@ impl std::str::FromStr for TaskStatus {
     }
 }
 
+// Records of listing commands, used for both table and structured output.
+// Durations are in seconds, timestamps without `_local` suffix are ISO 8601 UTC.
+
+#[derive(Serialize, Default)]
+struct TaskRecord<'a> {
+    taskname: &'a str,
+    tags: String, // comma separated
+    notes: Option<&'a str>,
+    spent: i32,
+    spent_text: String,
+    allocated: i32,
+    allocated_text: String,
+    duedate: Option<&'a str>,
+    done: bool,
+    created: String,
+    created_local: String,
+}
+
+#[derive(Serialize, Default)]
+struct TaskTreeRecord<'a> {
+    taskname: &'a str,
+    depth: usize,
+    spent: i32,
+    spent_text: String,
+    own_spent: i32,
+    own_spent_text: String,
+    allocated: i32,
+    allocated_text: String,
+    duedate: Option<&'a str>,
+    done: Option<bool>,
+}
+
+#[derive(Serialize, Default)]
+struct StatusRecord<'a> {
+    taskname: &'a str,
+    current_spent: i32, // without breaks
+    current_spent_text: String,
+    current_wall: i32, // including breaks
+    current_wall_text: String,
+    paused: bool,
+    over_max_session: bool,
+    started: String,
+    started_local: String,
+    spent: i32,
+    spent_text: String,
+}
+
+#[derive(Serialize, Default)]
+struct WorklogRecord<'a> {
+    id: i32,
+    taskname: &'a str,
+    started: String,
+    started_local: String,
+    stopped: Option<String>,
+    stopped_local: Option<String>,
+    duration: i32,
+    duration_text: String,
+    running: bool,
+    ignored: bool,
+    notes: Option<&'a str>,
+}
+
+#[derive(Serialize, Default)]
+struct TrashRecord<'a> {
+    taskname: &'a str,
+    spent: i32,
+    spent_text: String,
+    deleted: String,
+    deleted_local: String,
+}
+
+#[derive(Serialize, Default)]
+struct RecurRecord<'a> {
+    taskname: &'a str,
+    rule: &'a str,
+    duedate: Option<&'a str>,
+    done: bool,
+}
+
+#[derive(Serialize, Default)]
+struct HistoryRecord<'a> {
+    id: i32,
+    command: &'a str,
+    created: String,
+    created_local: String,
+    changes: String, // separated by `; `
+}
+
+#[derive(Serialize, Default)]
+struct TagRecord<'a> {
+    tag: &'a str,
+    tasks: i32,
+    spent: i32,
+    spent_text: String,
+}
+
+#[derive(Serialize, Default)]
+struct ReportRecord<'a> {
+    date: String,
+    taskname: &'a str,
+    spent: i32,
+    spent_text: String,
+    breaks: i32,
+    breaks_text: String,
+    notes: String, // of all sessions, separated by `; `
+}
+
 pub fn parse_cli() -> Result<(), BoxError> {
+    if std::env::args().nth(1).as_deref() == Some("__complete-tasks") {
+        return complete_tasks(&CompleteTasksOpts::from_iter(std::env::args().skip(1)));
+    }
     let args = Cli::from_args();
     debug!("Hello, world!");
     debug!("{:?}", &args);
 
+    if let Sub::Completions(opts) = &args.cmd {
+        return completions::write_completions(
+            &mut Cli::clap(),
+            opts.shell,
+            &mut std::io::stdout(),
+        );
+    }
+    if let Sub::Prompt(opts) = &args.cmd {
+        // before creating the config or reading max_session, which could print
+        // into the shell prompt, and errors leave the segment empty
+        let _ = prompt(args.config.as_ref(), args.dbfile.as_ref(), opts);
+        return Ok(());
+    }
+
     let conf: Option<&PathBuf> = match &args.config {
         Some(val) => Some(&val),
         None => None,
@@ -167,38 +825,291 @@ pub fn parse_cli() -> Result<(), BoxError> {
             .insert("path".to_owned(), path.to_string_lossy().to_string());
     }
 
+    let max_session = match &config.max_session {
+        Some(text) => Some(parse_duration(text)?),
+        None => None,
+    };
+    let tracker = Tracker::new(&config)?;
+    let output = args.output;
     match &args.cmd {
+        Sub::Undo(args) => undo(&tracker, args),
+        Sub::History(args) => history(&tracker, args, output),
+        cmd if cmd.changes_data() => {
+            // before opening the undo operation, an interrupted prompt would leave
+            // it open
+            let stops = match ask(&tracker, cmd, max_session)? {
+                Some(stops) => stops,
+                None => return Ok(()),
+            };
+            tracker.record(&command_line(), || {
+                run_command(&tracker, cmd, output, max_session, &stops)
+            })
+        }
+        cmd => run_command(&tracker, cmd, output, max_session, &[]),
+    }
+}
+
+/// Tasks to stop with the time to stop each at, `None` for now.
+type Stops = Vec<(String, Option<DateTime<Utc>>)>;
+
+/// Ask the user what `cmd` needs before it changes any data.
+///
+/// Returns when to stop each task of `stop` and `stopall`, `None` when the user
+/// declines a confirmation.
+fn ask(tracker: &Tracker, cmd: &Sub, max_session: Option<i32>) -> Result<Option<Stops>, BoxError> {
+    let prompt = match cmd {
+        Sub::Stop(args) => {
+            let limit = max_session.map(|seconds| (seconds, args.truncate));
+            let at = args.time.to_utc()?;
+            return Ok(Some(session_ends(tracker, &args.name, at, limit)?));
+        }
+        Sub::StopAll(args) => {
+            let running_tasks = tracker.get_running_tasks(None, &[])?;
+            let tasknames: Vec<String> = running_tasks.iter().map(|t| t.name.to_owned()).collect();
+            let limit = max_session.map(|seconds| (seconds, args.truncate));
+            let at = args.time.to_utc()?;
+            return Ok(Some(session_ends(tracker, &tasknames, at, limit)?));
+        }
+        Sub::Delete(args) if !args.noconfirm => format!("Delete task {} ?", args.name),
+        Sub::Merge(args) if !args.noconfirm => {
+            format!("Merge task {} into {} ?", args.source, args.target)
+        }
+        Sub::Trash(args) => match &args.cmd {
+            TrashSub::Purge(args) if !args.noconfirm => {
+                "Permanently delete tasks in the trash with their worklogs ?".to_owned()
+            }
+            _ => return Ok(Some(Vec::new())),
+        },
+        Sub::Worklog(args) => match &args.cmd {
+            WorklogSub::Delete(args) if !args.noconfirm => {
+                format!("Delete worklog #{} ?", args.id)
+            }
+            _ => return Ok(Some(Vec::new())),
+        },
+        _ => return Ok(Some(Vec::new())),
+    };
+    if !Confirm::new().with_prompt(prompt).interact()? {
+        return Ok(None);
+    }
+    Ok(Some(Vec::new()))
+}
+
+/// Run `cmd`, stopping tasks at `stops` as answered by `ask`.
+fn run_command(
+    tracker: &Tracker,
+    cmd: &Sub,
+    output: OutputFormat,
+    max_session: Option<i32>,
+    stops: &[(String, Option<DateTime<Utc>>)],
+) -> Result<(), BoxError> {
+    match cmd {
         Sub::Create(args) => {
             let allocated = match args.allocated {
                 Some(val) => Some(val * 60), // mins to secs
                 None => None,
             };
-            ops::create_task(
-                &config,
+            let taskobj = tracker.create_task(
                 &args.name,
                 args.note.as_deref(),
                 allocated,
                 open_naivedate(args.duedate).as_deref(),
-            )
+            )?;
+            tracker.add_tags(&taskobj.taskname, &args.tags)?;
+            println!("{} created.", Style::new().bold().paint(taskobj.taskname));
+            Ok(())
         }
-        Sub::List(args) => list_tasks(&config, args),
-        Sub::Edit(args) => update_task(&config, args),
-        Sub::Delete(args) => delete_task(&config, args),
-        Sub::Start(args) => start_task(&config, args),
-        Sub::Stop(args) => stop_task(&config, args),
-        Sub::StopAll(args) => stop_all_tasks(&config, args),
-        Sub::Status(args) => tasks_status(&config, args),
+        Sub::List(args) => list_tasks(tracker, args, output),
+        Sub::Edit(args) => update_task(tracker, args),
+        Sub::Delete(args) => delete_task(tracker, args),
+        Sub::Merge(args) => merge_tasks(tracker, args),
+        Sub::Rename(args) => {
+            for (old, new) in tracker.rename_task(&args.old, &args.new, args.recursive)? {
+                println!(
+                    "{} renamed to {}.",
+                    Style::new().bold().paint(old),
+                    Style::new().bold().paint(new)
+                );
+            }
+            Ok(())
+        }
+        Sub::Start(args) => start_task(tracker, args),
+        Sub::Stop(args) => stop_worklogs(tracker, stops, args.message.as_deref()),
+        Sub::StopAll(_) => {
+            if stops.is_empty() {
+                println!("No running task");
+                return Ok(());
+            }
+            stop_worklogs(tracker, stops, None)
+        }
+        Sub::Switch(args) => {
+            let at = args.time.to_utc()?;
+            switch_task(tracker, &args.name, at, args.message.as_deref())
+        }
+        Sub::Pause(args) => pause_task(tracker, args),
+        Sub::Resume(args) => resume_task(tracker, args),
+        Sub::Log(args) => log_worklog(tracker, args),
+        Sub::Worklog(args) => match &args.cmd {
+            WorklogSub::List(args) => list_worklogs(tracker, args, output),
+            WorklogSub::Edit(args) => update_worklog(tracker, args),
+            WorklogSub::Delete(args) => delete_worklog(tracker, args),
+            WorklogSub::Ignore(args) => set_worklog_ignored(tracker, args, true),
+            WorklogSub::Unignore(args) => set_worklog_ignored(tracker, args, false),
+        },
+        Sub::Trash(args) => match &args.cmd {
+            TrashSub::List => list_trash(tracker, output),
+            TrashSub::Restore(args) => {
+                let taskobj = tracker.restore_task(&args.name)?;
+                println!("{} restored.", Style::new().bold().paint(taskobj.taskname));
+                Ok(())
+            }
+            TrashSub::Purge(args) => purge_trash(tracker, args),
+        },
+        Sub::Recur(args) => match &args.cmd {
+            RecurSub::List => list_recurring(tracker, output),
+            RecurSub::Set(args) => {
+                let taskobj = tracker.set_recurrence(&args.name, Some(&args.rule))?;
+                println!(
+                    "{} recurs {}.",
+                    Style::new().bold().paint(taskobj.taskname),
+                    args.rule
+                );
+                Ok(())
+            }
+            RecurSub::Clear(args) => {
+                let taskobj = tracker.set_recurrence(&args.name, None)?;
+                println!(
+                    "{} no longer recurs.",
+                    Style::new().bold().paint(taskobj.taskname)
+                );
+                Ok(())
+            }
+        },
+        Sub::Status(args) => tasks_status(tracker, args, output, max_session),
+        Sub::Report(args) => report(tracker, args, output),
+        Sub::Tags(_) => list_tags(tracker, output),
+        Sub::Export(args) => export(tracker, args),
+        Sub::Import(args) => import(tracker, args),
         _ => Ok(()),
     }
 }
 
-fn list_tasks(config: &config::Config, args: &ListOpts) -> Result<(), BoxError> {
-    let data = ops::list_tasks(&config, args.filter.as_deref(), Some(&args.status))?;
+/// Command line without the config and database options, as recorded in the
+/// undo log.
+fn command_line() -> String {
+    let mut words = vec!["ttrackr".to_owned()];
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" | "--dbfile" => {
+                args.next();
+            }
+            _ if arg.starts_with("--config=") || arg.starts_with("--dbfile=") => (),
+            _ if arg.contains(char::is_whitespace) => words.push(format!("\"{}\"", arg)),
+            _ => words.push(arg),
+        }
+    }
+    words.join(" ")
+}
+
+fn undo(tracker: &Tracker, args: &UndoOpts) -> Result<(), BoxError> {
+    let operations = tracker.history(Some(args.count))?;
+    if operations.is_empty() {
+        println!("Nothing to undo");
+        return Ok(());
+    }
+    if !args.noconfirm {
+        let commands: Vec<&str> = operations.iter().map(|op| op.command.as_str()).collect();
+        let prompt = format!("Undo {} ?", commands.join(", "));
+        if !Confirm::new().with_prompt(prompt).interact()? {
+            return Ok(());
+        }
+    }
+    for operation in tracker.undo(args.count)? {
+        println!("{} undone.", Style::new().bold().paint(operation.command));
+    }
+    Ok(())
+}
+
+fn history(tracker: &Tracker, args: &HistoryOpts, output: OutputFormat) -> Result<(), BoxError> {
+    let operations = tracker.history(Some(args.limit))?;
+    let mut records = Vec::with_capacity(operations.len());
+    for operation in operations.iter() {
+        let changes: Vec<String> = operation
+            .changes
+            .iter()
+            .map(|change| {
+                let mut text = format!("{} {} {}", change.table, change.key, change.kind.label());
+                if !change.columns.is_empty() {
+                    text.push_str(&format!(" ({})", change.columns.join(", ")));
+                }
+                text
+            })
+            .collect();
+        records.push(HistoryRecord {
+            id: operation.id,
+            command: &operation.command,
+            created: utc_to_iso8601(&operation.created),
+            created_local: utc_to_local_naive(&operation.created),
+            changes: changes.join("; "),
+        });
+    }
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+    if records.is_empty() {
+        println!("No history");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["ID", "Time", "Command", "Changes"]);
+    for row in records.iter() {
+        table.add_row(vec![
+            row.id.to_string(),
+            row.created_local.to_string(),
+            row.command.to_string(),
+            row.changes.replace("; ", "\n"),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+fn list_tasks(tracker: &Tracker, args: &ListOpts, output: OutputFormat) -> Result<(), BoxError> {
+    if args.tree {
+        return list_task_tree(tracker, args, output);
+    }
+    let data =
+        tracker.list_tasks_with_spent(args.filter.as_deref(), Some(&args.status), &args.tags)?;
+    let task_tags = tracker.get_task_tags()?;
     // debug!("result: {:#?}", data);
+    let mut records = Vec::with_capacity(data.len());
+    for row in data.iter() {
+        records.push(TaskRecord {
+            taskname: &row.task.taskname,
+            tags: task_tags
+                .get(&row.task.id)
+                .map_or(String::new(), |tags| tags.join(",")),
+            notes: row.task.notes.as_deref(),
+            spent: row.spent,
+            spent_text: fmt_duration(row.spent, false, "not started"),
+            allocated: row.task.allocated,
+            allocated_text: fmt_duration(row.task.allocated, true, "-"),
+            duedate: row.task.duedate.as_deref(),
+            done: row.task.done,
+            created: utc_to_iso8601(&row.task.created),
+            created_local: utc_to_local_naive(&row.task.created),
+        });
+    }
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+
     let mut table = Table::new();
     table.set_header(vec![
         "#",
         "Task",
+        "Tags",
         "Notes",
         "Spent",
         "Allocated",
@@ -206,89 +1117,870 @@ fn list_tasks(config: &config::Config, args: &ListOpts) -> Result<(), BoxError>
         "Done",
         "Created",
     ]);
-    for (i, row) in data.iter().enumerate() {
-        let spent = ops::get_total_spent(&config, &row.taskname)?;
+    for (i, row) in records.iter().enumerate() {
         table.add_row(vec![
             (i + 1).to_string(),
-            // (row.id).to_string(),
             row.taskname.to_string(),
-            unwrap_string(row.notes.as_ref(), "-"),
-            fmt_duration(spent, false, "not started"),
-            fmt_duration(row.allocated, true, "-"),
-            unwrap_string(row.duedate.as_ref(), "-"),
+            if row.tags.is_empty() {
+                String::from("-")
+            } else {
+                row.tags.to_string()
+            },
+            row.notes.unwrap_or("-").to_string(),
+            row.spent_text.to_string(),
+            row.allocated_text.to_string(),
+            row.duedate.unwrap_or("-").to_string(),
             row.done.to_string(),
-            utc_to_local_naive(&row.created)?.to_string(),
+            row.created_local.to_string(),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+fn list_task_tree(
+    tracker: &Tracker,
+    args: &ListOpts,
+    output: OutputFormat,
+) -> Result<(), BoxError> {
+    let data = tracker.get_task_tree(args.filter.as_deref(), Some(&args.status), &args.tags)?;
+    let records: Vec<TaskTreeRecord> = data
+        .iter()
+        .map(|node| TaskTreeRecord {
+            taskname: &node.name,
+            depth: node.depth,
+            spent: node.spent,
+            spent_text: fmt_duration(node.spent, false, "not started"),
+            own_spent: node.own_spent,
+            own_spent_text: match node.task {
+                Some(_) => fmt_duration(node.own_spent, false, "not started"),
+                None => String::from("-"),
+            },
+            allocated: node.allocated,
+            allocated_text: fmt_duration(node.allocated, true, "-"),
+            duedate: node.task.as_ref().and_then(|t| t.duedate.as_deref()),
+            done: node.task.as_ref().map(|t| t.done),
+        })
+        .collect();
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Task",
+        "Spent",
+        "Own Spent",
+        "Allocated",
+        "Due Date",
+        "Done",
+    ]);
+    for (node, row) in data.iter().zip(records.iter()) {
+        let mut name = "  ".repeat(node.depth);
+        name.push_str(node.label());
+        table.add_row(vec![
+            name,
+            row.spent_text.to_string(),
+            row.own_spent_text.to_string(),
+            row.allocated_text.to_string(),
+            row.duedate.unwrap_or("-").to_string(),
+            row.done.map_or(String::from("-"), |d| d.to_string()),
         ]);
     }
     println!("{}", table);
     Ok(())
 }
 
-fn update_task(config: &config::Config, args: &EditOpts) -> Result<(), BoxError> {
-    let allocated = args.allocated.unwrap_or(0) * 60; //
+fn update_task(tracker: &Tracker, args: &EditOpts) -> Result<(), BoxError> {
+    let allocated = args.allocated.map(|val| val * 60); // mins to secs
     let mut done: Option<bool> = None;
     if args.finish {
         done = Some(true);
     } else if args.incomplete {
         done = Some(false);
     }
-    ops::update_tasks(
-        config,
+    // tag before updating so that a renewed recurring task gets the tags too
+    tracker.add_tags(&args.name, &args.tags)?;
+    tracker.remove_tags(&args.name, &args.untags)?;
+    let taskobj = tracker.update_tasks(
         &args.name,
         args.note.as_deref(),
-        Some(allocated),
+        allocated,
         open_naivedate(args.duedate).as_deref(),
         done,
-    )
+    )?;
+    println!("{} updated.", Style::new().bold().paint(&taskobj.taskname));
+    if taskobj.taskname != args.name {
+        // completed recurring task, kept under a new name
+        let renewed = tracker.list_recurring()?;
+        if let Some(next) = renewed.iter().find(|t| t.taskname == args.name) {
+            println!(
+                "{} is due again on {}.",
+                Style::new().bold().paint(&next.taskname),
+                next.duedate.as_deref().unwrap_or("-")
+            );
+        }
+    }
+    Ok(())
 }
 
-fn delete_task(config: &config::Config, args: &DeleteOpts) -> Result<(), BoxError> {
-    if !args.noconfirm {
-        let mut prompt = "Delete task ".to_owned();
-        prompt.push_str(&args.name);
-        prompt.push_str(" ?");
-        if !Confirm::new().with_prompt(prompt).interact()? {
-            return Ok(());
+fn list_recurring(tracker: &Tracker, output: OutputFormat) -> Result<(), BoxError> {
+    let data = tracker.list_recurring()?;
+    let records: Vec<RecurRecord> = data
+        .iter()
+        .map(|taskobj| RecurRecord {
+            taskname: &taskobj.taskname,
+            rule: taskobj.recur.as_deref().unwrap_or_default(),
+            duedate: taskobj.duedate.as_deref(),
+            done: taskobj.done,
+        })
+        .collect();
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+    if records.is_empty() {
+        println!("No recurring tasks");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Task", "Rule", "Due date", "Done"]);
+    for row in records.iter() {
+        table.add_row(vec![
+            row.taskname.to_string(),
+            row.rule.to_string(),
+            row.duedate.unwrap_or("-").to_string(),
+            row.done.to_string(),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+fn delete_task(tracker: &Tracker, args: &DeleteOpts) -> Result<(), BoxError> {
+    if let Some(stopped) = tracker.delete_task(&args.name)? {
+        print_stopped(&stopped);
+    }
+    println!("{} moved to trash.", Style::new().bold().paint(&args.name));
+    Ok(())
+}
+
+fn list_trash(tracker: &Tracker, output: OutputFormat) -> Result<(), BoxError> {
+    let data = tracker.list_trash()?;
+    let mut records = Vec::with_capacity(data.len());
+    for row in data.iter() {
+        let deleted = row.task.deleted_at.unwrap_or(row.task.created);
+        records.push(TrashRecord {
+            taskname: &row.task.taskname,
+            spent: row.spent,
+            spent_text: fmt_duration(row.spent, false, "-"),
+            deleted: utc_to_iso8601(&deleted),
+            deleted_local: utc_to_local_naive(&deleted),
+        });
+    }
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+    if records.is_empty() {
+        println!("Trash is empty");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Task", "Spent", "Deleted"]);
+    for row in records.iter() {
+        table.add_row(vec![
+            row.taskname.to_string(),
+            row.spent_text.to_string(),
+            row.deleted_local.to_string(),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+fn purge_trash(tracker: &Tracker, args: &TrashPurgeOpts) -> Result<(), BoxError> {
+    let older_than = args
+        .older_than
+        .map(|seconds| get_timestamp() - Duration::seconds(i64::from(seconds)));
+    let purged = tracker.purge_trash(older_than)?;
+    for name in purged.iter() {
+        println!("{} deleted.", Style::new().bold().paint(name));
+    }
+    if purged.is_empty() {
+        println!("Nothing to purge");
+    }
+    Ok(())
+}
+
+fn merge_tasks(tracker: &Tracker, args: &MergeOpts) -> Result<(), BoxError> {
+    let event = tracker.merge_tasks(
+        &args.source,
+        &args.target,
+        args.notes,
+        args.allocated,
+        args.force,
+    )?;
+    if let Some(stopped) = &event.stopped {
+        print_stopped(stopped);
+    }
+    print!(
+        "{} merged into {}, {} worklogs moved",
+        Style::new().bold().paint(&args.source),
+        Style::new().bold().paint(&event.task.taskname),
+        event.worklogs
+    );
+    if event.adjusted > 0 {
+        print!(
+            " ({} cut short or ignored where they overlapped)",
+            event.adjusted
+        );
+    }
+    println!(".");
+    Ok(())
+}
+
+fn start_task(tracker: &Tracker, args: &StartOpts) -> Result<(), BoxError> {
+    let at = args.time.to_utc()?;
+    if tracker.is_single_active() && args.name.len() == 1 {
+        return switch_task(tracker, &args.name[0], at, args.message.as_deref());
+    }
+    let events = tracker.start_worklogs(&args.name, at, args.message.as_deref())?;
+    for event in events.iter() {
+        match event {
+            Ok(event) => print_started(event),
+            Err(err) => print_skipped(err),
         }
     }
-    let exists = ops::check_task_exists(config, &args.name)?;
-    if !exists {
-        return Err(TaskNotFound.into());
+    Ok(())
+}
+
+fn switch_task(
+    tracker: &Tracker,
+    name: &str,
+    at: Option<DateTime<Utc>>,
+    message: Option<&str>,
+) -> Result<(), BoxError> {
+    let switched = tracker.switch_worklog(name, at, message)?;
+    for event in switched.stopped.iter() {
+        print_stopped(event);
+    }
+    match &switched.started {
+        Some(event) => print_started(event),
+        None => println!("{} is already running.", Style::new().bold().paint(name)),
     }
-    ops::delete_task(config, &args.name)
+    Ok(())
 }
 
-fn start_task(config: &config::Config, args: &StartOpts) -> Result<(), BoxError> {
-    ops::start_worklogs(config, &args.name)
+/// When to stop each of tasks `names`, ending sessions longer than `limit` as
+/// chosen by `end_of_session`.
+///
+/// `limit` is `max_session` in seconds and the `--truncate` flag.
+fn session_ends(
+    tracker: &Tracker,
+    names: &[String],
+    at: Option<DateTime<Utc>>,
+    limit: Option<(i32, bool)>,
+) -> Result<Stops, BoxError> {
+    let running = match limit {
+        Some(_) => tracker.get_running_tasks(None, &[])?,
+        None => Vec::new(),
+    };
+    let mut stops = Vec::with_capacity(names.len());
+    for name in names.iter() {
+        let end = match (limit, running.iter().find(|t| &t.name == name)) {
+            (Some((max_session, truncate)), Some(task)) => {
+                end_of_session(task, at, max_session, truncate)?
+            }
+            _ => at,
+        };
+        stops.push((name.to_owned(), end));
+    }
+    Ok(stops)
 }
 
-fn stop_task(config: &config::Config, args: &StopOpts) -> Result<(), BoxError> {
-    ops::stop_worklogs(config, &args.name)
+/// Stop tasks at `stops` in one batch, so a failing task leaves the others running.
+fn stop_worklogs(
+    tracker: &Tracker,
+    stops: &[(String, Option<DateTime<Utc>>)],
+    message: Option<&str>,
+) -> Result<(), BoxError> {
+    for event in tracker.stop_worklogs_at(stops, message)?.iter() {
+        match event {
+            Ok(event) => print_stopped(event),
+            Err(err) => print_skipped(err),
+        }
+    }
+    Ok(())
 }
 
-fn stop_all_tasks(config: &config::Config, _args: &StopAllOpts) -> Result<(), BoxError> {
-    let running_tasks = ops::get_running_tasks(config, None)?;
-    let tasknames: Vec<String> = running_tasks.iter().map(|t| t.name.to_owned()).collect();
-    ops::stop_worklogs(config, &tasknames)
+/// Choose when to stop `task` if its session at `at` is longer than `max_session`.
+///
+/// With `truncate` the session ends at the threshold, or when its open break
+/// started if that is later. Otherwise the user is asked when running in a
+/// terminal, scripts keep `at` and get a warning.
+fn end_of_session(
+    task: &RunningTask,
+    at: Option<DateTime<Utc>>,
+    max_session: i32,
+    truncate: bool,
+) -> Result<Option<DateTime<Utc>>, BoxError> {
+    let end = at.unwrap_or_else(get_timestamp);
+    let wall = seconds_between(&task.started, &end)?;
+    if wall <= max_session {
+        return Ok(at);
+    }
+    let mut threshold = task.started + Duration::seconds(i64::from(max_session));
+    if let Some(paused_at) = task.paused_at {
+        // a session cannot end before its open break starts
+        threshold = std::cmp::max(threshold, paused_at);
+    }
+    if truncate {
+        return Ok(Some(threshold));
+    }
+    let taskname = Style::new().bold().paint(&task.name);
+    if !std::io::stdin().is_terminal() {
+        eprintln!(
+            "{} ran longer than max_session, pass --truncate to end it at {}.",
+            taskname,
+            utc_to_local_naive(&threshold)
+        );
+        return Ok(at);
+    }
+    let items = [
+        format!("End at {} (max_session)", utc_to_local_naive(&threshold)),
+        String::from("End at another time"),
+        format!("Keep ending at {}", utc_to_local_naive(&end)),
+    ];
+    let prompt = format!(
+        "{} has been running for {}",
+        taskname,
+        fmt_duration(wall, false, "-")
+    );
+    match Select::new()
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact()?
+    {
+        0 => Ok(Some(threshold)),
+        1 => {
+            let text = Input::<String>::new().with_prompt("End time").interact()?;
+            Ok(Some(local_to_utc(&parse_local_time(&text)?)?))
+        }
+        _ => Ok(at),
+    }
+}
+
+fn print_started(event: &WorklogEvent) {
+    println!(
+        "{} started at {}.",
+        Style::new().bold().paint(&event.taskname),
+        utc_to_local_naive(&event.timestamp)
+    );
+}
+
+fn print_stopped(event: &WorklogEvent) {
+    print!(
+        "{} stopped at {}",
+        Style::new().bold().paint(&event.taskname),
+        utc_to_local_naive(&event.timestamp)
+    );
+    if event.completed {
+        print!(" [{}]", Style::new().bold().paint("done"));
+    }
+    println!(".");
 }
 
-fn tasks_status(config: &config::Config, args: &StatusOpts) -> Result<(), BoxError> {
-    let tasks = ops::get_running_tasks(config, args.filter.as_deref())?;
-    if tasks.is_empty() {
+fn pause_task(tracker: &Tracker, args: &PauseOpts) -> Result<(), BoxError> {
+    let at = args.time.to_utc()?;
+    let events = tracker.pause_worklogs(&args.name, at)?;
+    for event in events.iter() {
+        match event {
+            Ok(event) => println!(
+                "{} paused at {}.",
+                Style::new().bold().paint(&event.taskname),
+                utc_to_local_naive(&event.timestamp)
+            ),
+            Err(err) => print_skipped(err),
+        }
+    }
+    Ok(())
+}
+
+fn resume_task(tracker: &Tracker, args: &ResumeOpts) -> Result<(), BoxError> {
+    let at = args.time.to_utc()?;
+    let events = tracker.resume_worklogs(&args.name, at)?;
+    for event in events.iter() {
+        match event {
+            Ok(event) => println!(
+                "{} resumed at {}.",
+                Style::new().bold().paint(&event.taskname),
+                utc_to_local_naive(&event.timestamp)
+            ),
+            Err(err) => print_skipped(err),
+        }
+    }
+    Ok(())
+}
+
+/// Print why a task was skipped by `start`, `stop`, `pause` or `resume`.
+fn print_skipped(err: &Error) {
+    match err {
+        Error::TaskIsCompleted { taskname } => eprintln!(
+            "Cannot start completed task: {}",
+            Style::new().bold().paint(taskname)
+        ),
+        Error::TaskIsAlreadyRunning { taskname } => {
+            eprintln!(
+                "{} is already running.",
+                Style::new().bold().paint(taskname)
+            )
+        }
+        Error::TaskIsNotRunning { taskname } => {
+            eprintln!("{} is not running", Style::new().bold().paint(taskname))
+        }
+        Error::AnotherTaskIsRunning { taskname } => eprintln!(
+            "{} is running, use switch to stop it first.",
+            Style::new().bold().paint(taskname)
+        ),
+        Error::TaskIsPaused { taskname } => {
+            eprintln!("{} is already paused.", Style::new().bold().paint(taskname))
+        }
+        Error::TaskIsNotPaused { taskname } => {
+            eprintln!("{} is not paused", Style::new().bold().paint(taskname))
+        }
+        _ => eprintln!("{}", err),
+    }
+}
+
+fn log_worklog(tracker: &Tracker, args: &LogOpts) -> Result<(), BoxError> {
+    let started = local_to_utc(&parse_local_time(&args.start)?)?;
+    let stopped = match (&args.end, args.duration) {
+        (Some(end), _) => local_to_utc(&parse_local_time(end)?)?,
+        (None, Some(seconds)) => started + Duration::seconds(i64::from(seconds)),
+        (None, None) => unreachable!(),
+    };
+    let worklog = tracker.log_worklog(&args.name, started, stopped)?;
+    println!(
+        "{} logged {}.",
+        Style::new().bold().paint(&args.name),
+        fmt_duration(worklog.duration, false, "-")
+    );
+    Ok(())
+}
+
+fn list_worklogs(
+    tracker: &Tracker,
+    args: &WorklogListOpts,
+    output: OutputFormat,
+) -> Result<(), BoxError> {
+    let data = tracker.list_worklogs(args.filter.as_deref(), args.all, args.limit)?;
+    let now = get_timestamp();
+    let mut records = Vec::with_capacity(data.len());
+    for (worklog, task) in data.iter() {
+        let duration = match &worklog.stopped {
+            Some(_) => worklog.duration,
+            None => seconds_between(&worklog.started, &now)?,
+        };
+        records.push(WorklogRecord {
+            id: worklog.id,
+            taskname: &task.taskname,
+            started: utc_to_iso8601(&worklog.started),
+            started_local: utc_to_local_naive(&worklog.started),
+            stopped: worklog.stopped.map(|s| utc_to_iso8601(&s)),
+            stopped_local: worklog.stopped.map(|s| utc_to_local_naive(&s)),
+            duration,
+            duration_text: fmt_duration(duration, false, "-"),
+            running: worklog.stopped.is_none(),
+            ignored: worklog.ignored,
+            notes: worklog.notes.as_deref(),
+        });
+    }
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+    if records.is_empty() {
+        println!("No worklog");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    let mut header = vec!["ID", "Task", "Started", "Stopped", "Duration", "Notes"];
+    if args.all {
+        header.push("Ignored");
+    }
+    table.set_header(header);
+    for row in records.iter() {
+        let mut cells = vec![
+            row.id.to_string(),
+            row.taskname.to_string(),
+            row.started_local.to_string(),
+            row.stopped_local
+                .as_deref()
+                .unwrap_or("running")
+                .to_string(),
+            row.duration_text.to_string(),
+            row.notes.unwrap_or("-").to_string(),
+        ];
+        if args.all {
+            cells.push(row.ignored.to_string());
+        }
+        table.add_row(cells);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+fn update_worklog(tracker: &Tracker, args: &WorklogEditOpts) -> Result<(), BoxError> {
+    let started = match &args.start {
+        Some(start) => Some(local_to_utc(&parse_local_time(start)?)?),
+        None => None,
+    };
+    let stopped = match &args.end {
+        Some(end) => Some(local_to_utc(&parse_local_time(end)?)?),
+        None => None,
+    };
+    let worklog = tracker.update_worklog(args.id, started, stopped)?;
+    println!("Worklog #{} updated.", worklog.id);
+    Ok(())
+}
+
+fn delete_worklog(tracker: &Tracker, args: &WorklogDeleteOpts) -> Result<(), BoxError> {
+    tracker.delete_worklog(args.id)?;
+    println!("Worklog #{} deleted.", args.id);
+    Ok(())
+}
+
+fn set_worklog_ignored(
+    tracker: &Tracker,
+    args: &WorklogIdOpts,
+    value: bool,
+) -> Result<(), BoxError> {
+    tracker.set_worklog_ignored(args.id, value)?;
+    println!(
+        "Worklog #{} {}.",
+        args.id,
+        if value { "ignored" } else { "unignored" }
+    );
+    Ok(())
+}
+
+fn tasks_status(
+    tracker: &Tracker,
+    args: &StatusOpts,
+    output: OutputFormat,
+    max_session: Option<i32>,
+) -> Result<(), BoxError> {
+    let tasks = tracker.get_running_tasks(args.filter.as_deref(), &args.tags)?;
+    let mut records = Vec::with_capacity(tasks.len());
+    for row in tasks.iter() {
+        records.push(StatusRecord {
+            taskname: &row.name,
+            current_spent: row.current_spent,
+            current_spent_text: fmt_duration(row.current_spent, false, "-"),
+            current_wall: row.current_wall,
+            current_wall_text: fmt_duration(row.current_wall, false, "-"),
+            paused: row.paused_at.is_some(),
+            over_max_session: max_session.is_some_and(|seconds| row.current_wall > seconds),
+            started: utc_to_iso8601(&row.started),
+            started_local: utc_to_local_naive(&row.started),
+            spent: row.spent,
+            spent_text: fmt_duration(row.spent, false, "-"),
+        });
+    }
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+    if records.is_empty() {
         println!("No running task");
         return Ok(());
     }
+
     let mut table = Table::new();
-    table.set_header(vec!["#", "Task", "Spent", "Last Started", "Total Spent"]);
-    for (i, row) in tasks.iter().enumerate() {
+    table.set_header(vec![
+        "#",
+        "Task",
+        "Spent",
+        "Wall",
+        "Last Started",
+        "Total Spent",
+    ]);
+    for (i, row) in records.iter().enumerate() {
+        let mut taskname = row.taskname.to_string();
+        if row.paused {
+            taskname.push_str(" (paused)");
+        }
+        if row.over_max_session {
+            taskname.push_str(" (over max_session)");
+        }
         table.add_row(vec![
             (i + 1).to_string(),
-            row.name.to_string(),
-            fmt_duration(row.current_spent, false, "-"),
-            utc_to_local_naive(&row.started)?.to_string(),
-            fmt_duration(row.spent, false, "-"),
+            taskname,
+            row.current_spent_text.to_string(),
+            row.current_wall_text.to_string(),
+            row.started_local.to_string(),
+            row.spent_text.to_string(),
+        ]);
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+/// Open the database for reading without creating the config file, `None` when
+/// there is no config or database yet.
+fn open_existing(
+    config: Option<&PathBuf>,
+    dbfile: Option<&PathBuf>,
+) -> Result<Option<Tracker>, BoxError> {
+    let dbpath = match dbfile {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => {
+            let cfgpath = config::get_config_path(config);
+            if !cfgpath.exists() {
+                return Ok(None);
+            }
+            match config::Config::load(Some(&cfgpath))?.database.get("path") {
+                Some(path) => path.to_owned(),
+                None => return Ok(None),
+            }
+        }
+    };
+    Ok(Tracker::open_read_only(&dbpath)?)
+}
+
+/// Print task names for completion scripts, one per line.
+///
+/// Prints nothing without a config file or database, never creating them.
+fn complete_tasks(args: &CompleteTasksOpts) -> Result<(), BoxError> {
+    let tracker = match open_existing(args.config.as_ref(), args.dbfile.as_ref())? {
+        Some(tracker) => tracker,
+        None => return Ok(()),
+    };
+    let mut names: Vec<String> = if args.running {
+        tracker
+            .list_running()?
+            .into_iter()
+            .map(|t| t.name)
+            .collect()
+    } else {
+        let status = if args.incomplete { "incomplete" } else { "all" };
+        tracker
+            .list_tasks(None, Some(status), &[])?
+            .into_iter()
+            .map(|t| t.taskname)
+            .collect()
+    };
+    names.sort();
+    names.dedup();
+    for name in names.iter() {
+        println!("{}", name);
+    }
+    Ok(())
+}
+
+/// Print a shell prompt segment for the running tasks, nothing when idle.
+///
+/// Skips migrations to keep shell prompts fast.
+fn prompt(
+    config: Option<&PathBuf>,
+    dbfile: Option<&PathBuf>,
+    args: &PromptOpts,
+) -> Result<(), BoxError> {
+    let tracker = match open_existing(config, dbfile)? {
+        Some(tracker) => tracker,
+        None => return Ok(()),
+    };
+    let running = tracker.list_running()?;
+    let last = match running.first() {
+        Some(task) => task,
+        None => return Ok(()),
+    };
+    let segment = args
+        .format
+        .replace("{task}", &last.name)
+        .replace("{elapsed}", &fmt_compact_duration(last.current_spent))
+        .replace("{total}", &fmt_compact_duration(last.spent))
+        .replace("{count}", &running.len().to_string());
+    println!("{}", segment);
+    Ok(())
+}
+
+fn list_tags(tracker: &Tracker, output: OutputFormat) -> Result<(), BoxError> {
+    let data = tracker.list_tags()?;
+    let records: Vec<TagRecord> = data
+        .iter()
+        .map(|row| TagRecord {
+            tag: &row.tag.name,
+            tasks: row.tasks,
+            spent: row.spent,
+            spent_text: fmt_duration(row.spent, false, "-"),
+        })
+        .collect();
+    if output != OutputFormat::Table {
+        return print_records(output, &records);
+    }
+    if records.is_empty() {
+        println!("No tag");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table.set_header(vec!["Tag", "Tasks", "Spent"]);
+    for row in records.iter() {
+        table.add_row(vec![
+            row.tag.to_string(),
+            row.tasks.to_string(),
+            row.spent_text.to_string(),
         ]);
     }
     println!("{}", table);
     Ok(())
 }
+
+fn report(tracker: &Tracker, args: &ReportOpts, output: OutputFormat) -> Result<(), BoxError> {
+    let (from, to) = args.period();
+    if to < from {
+        return Err(Error::InvalidTimeRange.into());
+    }
+    let entries = tracker.get_report(from, to, args.filter.as_deref(), &args.tags)?;
+    if output != OutputFormat::Table {
+        let records: Vec<ReportRecord> = entries
+            .iter()
+            .map(|entry| ReportRecord {
+                date: entry.date.to_string(),
+                taskname: &entry.taskname,
+                spent: entry.spent,
+                spent_text: fmt_duration(entry.spent, false, "-"),
+                breaks: entry.breaks,
+                breaks_text: fmt_duration(entry.breaks, false, "-"),
+                notes: entry.notes.join("; "),
+            })
+            .collect();
+        return print_records(output, &records);
+    }
+    if from == to {
+        println!("Report for {}", from);
+    } else {
+        println!("Report for {} to {}", from, to);
+    }
+    if entries.is_empty() {
+        println!("No worklog");
+        return Ok(());
+    }
+
+    // spent and breaks per task
+    let mut by_task: BTreeMap<&str, (i32, i32)> = BTreeMap::new();
+    for entry in entries.iter() {
+        let sums = by_task.entry(&entry.taskname).or_insert((0, 0));
+        sums.0 += entry.spent;
+        sums.1 += entry.breaks;
+    }
+    let total: i32 = by_task.values().map(|sums| sums.0).sum();
+    let total_breaks: i32 = by_task.values().map(|sums| sums.1).sum();
+
+    let mut table = Table::new();
+    table.set_header(vec!["Task", "Spent", "Breaks"]);
+    for (taskname, (spent, breaks)) in by_task.iter() {
+        table.add_row(vec![
+            taskname.to_string(),
+            fmt_duration(*spent, false, "-"),
+            fmt_duration(*breaks, false, "-"),
+        ]);
+    }
+    table.add_row(vec![
+        String::from("Total"),
+        fmt_duration(total, false, "-"),
+        fmt_duration(total_breaks, false, "-"),
+    ]);
+    println!("{}", table);
+
+    let mut table = Table::new();
+    table.set_header(vec!["Date", "Task", "Spent", "Breaks", "Notes"]);
+    for (i, entry) in entries.iter().enumerate() {
+        let first_of_day = i == 0 || entries[i - 1].date != entry.date;
+        table.add_row(vec![
+            if first_of_day {
+                entry.date.to_string()
+            } else {
+                String::new()
+            },
+            entry.taskname.to_string(),
+            fmt_duration(entry.spent, false, "-"),
+            fmt_duration(entry.breaks, false, "-"),
+            if entry.notes.is_empty() {
+                String::from("-")
+            } else {
+                entry.notes.join("; ")
+            },
+        ]);
+        let last_of_day = i + 1 == entries.len() || entries[i + 1].date != entry.date;
+        if last_of_day && !first_of_day {
+            let day = entries.iter().filter(|e| e.date == entry.date);
+            let day_total: i32 = day.clone().map(|e| e.spent).sum();
+            let day_breaks: i32 = day.map(|e| e.breaks).sum();
+            table.add_row(vec![
+                String::new(),
+                String::from("Total"),
+                fmt_duration(day_total, false, "-"),
+                fmt_duration(day_breaks, false, "-"),
+                String::new(),
+            ]);
+        }
+    }
+    println!("{}", table);
+    Ok(())
+}
+
+fn export(tracker: &Tracker, args: &ExportOpts) -> Result<(), BoxError> {
+    let archive = tracker.export_archive()?;
+    let mut out: Box<dyn io::Write> = match &args.file {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+    if args.format == "ics" {
+        calendar::write_ics(&archive, args.todos, &mut out)?;
+    } else {
+        archive.write_to(&mut out)?;
+    }
+    Ok(())
+}
+
+fn import(tracker: &Tracker, args: &ImportOpts) -> Result<(), BoxError> {
+    if args.merge && args.from != "ttrackr" {
+        return Err(format!(
+            "--merge only applies to ttrackr archives, {} entries always join existing tasks",
+            args.from
+        )
+        .into());
+    }
+    let reader = BufReader::new(File::open(&args.file)?);
+    let summary = match args.from.as_ref() {
+        "timewarrior" => {
+            tracker.import_entries(&importers::read_timewarrior(reader)?, args.dry_run)?
+        }
+        "toggl" => tracker.import_entries(&importers::read_toggl(reader)?, args.dry_run)?,
+        _ => {
+            let archive = Archive::read_from(reader)?;
+            tracker.import_archive(&archive, args.merge, args.dry_run)?
+        }
+    };
+    if args.dry_run {
+        for name in summary.new_tasks.iter() {
+            println!("Would create task {}", Style::new().bold().paint(name));
+        }
+    }
+    println!(
+        "{} {} tasks ({} merged) and {} worklogs ({} skipped).",
+        if args.dry_run {
+            "Would import"
+        } else {
+            "Imported"
+        },
+        summary.tasks_created + summary.tasks_merged,
+        summary.tasks_merged,
+        summary.worklogs_created,
+        summary.worklogs_skipped
+    );
+    Ok(())
+}
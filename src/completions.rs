@@ -0,0 +1,155 @@
+// shell completion scripts that complete task names from the database
+use crate::utils::BoxError;
+use std::io::Write;
+use structopt::clap::{App, Shell};
+
+/// Subcommands taking task names, with the `__complete-tasks` flag selecting them.
+const TASK_COMMANDS: [(&[&str], &str); 3] = [
+    (&["start", "switch", "sw"], "--incomplete"),
+    (&["stop", "pause", "resume"], "--running"),
+    (&["edit", "delete", "del", "rename", "mv", "merge"], ""),
+];
+
+/// Options of task commands that take a value, which is not a task name.
+const VALUE_OPTIONS: &str = "-t|-d|-n|--note|--tag|--untag|--at|--ago|-m|--message|-o|--output";
+
+/// Write the completion script generated from `app` for `shell`, extended to
+/// complete task names by calling `ttrackr __complete-tasks`.
+pub fn write_completions(
+    app: &mut App,
+    shell: Shell,
+    out: &mut impl Write,
+) -> Result<(), BoxError> {
+    let mut generated = Vec::new();
+    app.gen_completions_to("ttrackr", shell, &mut generated);
+    let generated = String::from_utf8(generated)?;
+    let script = match shell {
+        Shell::Bash => format!("{}\n{}", generated, bash_tasks()),
+        Shell::Zsh => zsh_tasks(&generated),
+        Shell::Fish => format!("{}{}", generated, fish_tasks()),
+        Shell::Elvish => format!("{}\n{}", generated, elvish_tasks()),
+        _ => generated,
+    };
+    out.write_all(script.as_bytes())?;
+    Ok(())
+}
+
+/// Bash function wrapping the generated `_ttrackr`, registered in its place.
+fn bash_tasks() -> String {
+    let arms: Vec<String> = TASK_COMMANDS
+        .iter()
+        .map(|(commands, flag)| format!("        {}) filter=\"{}\" ;;", commands.join("|"), flag))
+        .collect();
+    format!(
+        r#"_ttrackr_tasks() {{
+    local i cur prev cmd="" filter=""
+    cur="${{COMP_WORDS[COMP_CWORD]}}"
+    prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${{COMP_WORDS[i]}}" in
+            --config|--dbfile|-o|--output) ((i++)) ;;
+            -*) ;;
+            *) cmd="${{COMP_WORDS[i]}}"; break ;;
+        esac
+    done
+    case "${{cmd}}" in
+{arms}
+        *) _ttrackr; return ;;
+    esac
+    case "${{prev}}" in
+        {options}) _ttrackr; return ;;
+    esac
+    if [[ ${{cur}} == -* ]]; then
+        _ttrackr
+        return
+    fi
+    # task names contain `::`, which bash splits words at
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n : cur
+    fi
+    COMPREPLY=( $(compgen -W "$(ttrackr __complete-tasks ${{filter}} 2>/dev/null)" -- "${{cur}}") )
+    if declare -F __ltrim_colon_completions >/dev/null; then
+        __ltrim_colon_completions "${{cur}}"
+    fi
+}}
+
+complete -F _ttrackr_tasks -o bashdefault -o default ttrackr
+"#,
+        arms = arms.join("\n"),
+        options = VALUE_OPTIONS
+    )
+}
+
+/// Zsh function completing task names, taking `__complete-tasks` flags.
+const ZSH_TASKS: &str = r#"_ttrackr_tasks() {
+    local -a tasks
+    tasks=(${(f)"$(ttrackr __complete-tasks "$@" 2>/dev/null)"})
+    compadd -a tasks
+}
+
+"#;
+
+/// Generated zsh script with the task name arguments of task commands completed
+/// by `_ttrackr_tasks` instead of `_files`.
+fn zsh_tasks(generated: &str) -> String {
+    let mut script = String::with_capacity(generated.len() + ZSH_TASKS.len());
+    let mut flag: Option<&str> = None;
+    for line in generated.lines() {
+        if line.starts_with('(') && line.ends_with(')') {
+            let command = &line[1..line.len() - 1];
+            flag = TASK_COMMANDS
+                .iter()
+                .find(|(commands, _)| commands.contains(&command))
+                .map(|(_, flag)| *flag);
+        }
+        if line == "_ttrackr \"$@\"" {
+            script.push_str(ZSH_TASKS);
+        }
+        match flag {
+            Some(flag) if line.contains("-- Task name") => {
+                let action = format!(":_ttrackr_tasks {}", flag);
+                script.push_str(&line.replace(":_files", action.trim_end()));
+            }
+            _ => script.push_str(line),
+        }
+        script.push('\n');
+    }
+    script
+}
+
+/// Fish completions added to the generated ones.
+fn fish_tasks() -> String {
+    let mut script = String::new();
+    for (commands, flag) in TASK_COMMANDS.iter() {
+        let call = format!("ttrackr __complete-tasks {}", flag);
+        script.push_str(&format!(
+            "complete -c ttrackr -n \"__fish_seen_subcommand_from {}\" -f -a \"({})\"\n",
+            commands.join(" "),
+            call.trim_end()
+        ));
+    }
+    script
+}
+
+/// Elvish completer wrapping the generated one.
+fn elvish_tasks() -> String {
+    let mut filters = Vec::new();
+    for (commands, flag) in TASK_COMMANDS.iter() {
+        for command in commands.iter() {
+            filters.push(format!("&{}=[{}]", command, flag));
+        }
+    }
+    format!(
+        r#"ttrackr-generated-completer = $edit:completion:arg-completer[ttrackr]
+edit:completion:arg-completer[ttrackr] = [@words]{{
+    filters = [{filters}]
+    if (and (> (count $words) 2) (has-key $filters $words[1]) (not (has-prefix $words[-1] '-'))) {{
+        ttrackr __complete-tasks $@filters[$words[1]]
+    }} else {{
+        $ttrackr-generated-completer $@words
+    }}
+}}
+"#,
+        filters = filters.join(" ")
+    )
+}
@@ -9,6 +9,10 @@ use std::path::PathBuf;
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct Config {
     pub autodone: bool,
+    #[serde(default)] // missing in config files of older versions
+    pub single_active: bool,
+    #[serde(default)]
+    pub max_session: Option<String>, // duration such as `10h`, see `parse_duration`
     pub database: HashMap<String, String>,
 }
 
@@ -16,6 +20,8 @@ impl Config {
     pub fn new() -> Self {
         Config {
             autodone: false,
+            single_active: false,
+            max_session: None,
             database: HashMap::<String, String>::new(),
         }
     }
@@ -94,6 +100,8 @@ mod test {
         conf.save_to(&mut file)?;
         let check = Config::load(Some(&file.path().to_path_buf()))?;
         assert_eq!(conf.autodone, check.autodone);
+        assert_eq!(conf.single_active, check.single_active);
+        assert_eq!(conf.max_session, check.max_session);
         assert_eq!(conf.database, conf.database);
         Ok(())
     }
@@ -0,0 +1,86 @@
+// portable JSON archive of the whole database
+use super::models;
+use crate::error::Error;
+use serde_derive::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Version of the archive format written by `export`.
+pub const ARCHIVE_VERSION: u32 = 2;
+
+/// Every `task`, `worklog`, `pause`, `tag` and `task_tag` row of a database.
+///
+/// Rows refer to each other by the ids found in the same archive, timestamps
+/// are UTC in the same format as stored in the database. Version 1 archives
+/// have no pauses and tags.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    pub exported: String, // ISO 8601 UTC
+    pub tasks: Vec<models::Task>,
+    pub worklogs: Vec<models::Worklog>,
+    #[serde(default)]
+    pub pauses: Vec<models::Pause>,
+    #[serde(default)]
+    pub tags: Vec<models::Tag>,
+    #[serde(default)]
+    pub task_tags: Vec<models::TaskTag>,
+}
+
+impl Archive {
+    pub fn write_to(&self, out: &mut impl Write) -> Result<(), Error> {
+        serde_json::to_writer_pretty(&mut *out, self)?;
+        writeln!(out)?;
+        Ok(())
+    }
+
+    pub fn read_from(input: impl Read) -> Result<Self, Error> {
+        let archive: Archive = serde_json::from_reader(input)?;
+        if archive.version > ARCHIVE_VERSION {
+            return Err(Error::UnsupportedArchive {
+                version: archive.version,
+            });
+        }
+        Ok(archive)
+    }
+}
+
+/// Counts of rows affected by an import.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub tasks_created: usize,
+    pub tasks_merged: usize,
+    pub worklogs_created: usize,
+    pub worklogs_skipped: usize,
+    pub new_tasks: Vec<String>, // names of created tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::BoxError;
+
+    #[test]
+    fn read_archive() -> Result<(), BoxError> {
+        let content = r#"{
+            "version": 1,
+            "exported": "2020-05-10T12:00:00Z",
+            "tasks": [{
+                "id": 3, "created": "2020-05-10 08:00:00", "taskname": "task1",
+                "notes": null, "allocated": 0, "duedate": null, "done": false
+            }],
+            "worklogs": [{
+                "id": 1, "task_id": 3, "started": "2020-05-10 09:00:00",
+                "stopped": "2020-05-10 10:00:00", "duration": 3600, "ignored": false
+            }]
+        }"#;
+        let archive = Archive::read_from(content.as_bytes())?;
+        assert_eq!(archive.tasks[0].taskname, "task1");
+        assert_eq!(archive.worklogs[0].task_id, 3);
+        assert!(archive.pauses.is_empty());
+        assert!(archive.tags.is_empty() && archive.task_tags.is_empty());
+
+        let newer = content.replace(r#""version": 1"#, r#""version": 99"#);
+        assert!(Archive::read_from(newer.as_bytes()).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+// iCalendar (RFC 5545) rendering of an archive
+use super::archive::Archive;
+use super::models;
+use crate::error::Error;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+use std::io::Write;
+
+const PRODID: &str = "-//ttrackr//ttrackr//EN";
+/// Content lines longer than this many octets are folded.
+const MAX_LINE: usize = 75;
+
+/// Write a VCALENDAR with a VEVENT for each stopped, non-ignored worklog of
+/// `archive` and, with `todos`, a VTODO for each task with a due date. Tasks in
+/// the trash are left out.
+///
+/// UIDs are derived from row ids, so importing a re-export into a calendar app
+/// updates the previously imported items.
+pub fn write_ics(archive: &Archive, todos: bool, out: &mut impl Write) -> Result<(), Error> {
+    let dtstamp = DateTime::parse_from_rfc3339(&archive.exported)
+        .map_err(|_| Error::InvalidTimestamp {
+            value: archive.exported.to_owned(),
+        })?
+        .with_timezone(&Utc)
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string();
+    let tasks: HashMap<i32, &models::Task> = archive
+        .tasks
+        .iter()
+        .filter(|t| t.deleted_at.is_none())
+        .map(|t| (t.id, t))
+        .collect();
+
+    write_line(out, "BEGIN:VCALENDAR")?;
+    write_line(out, "VERSION:2.0")?;
+    write_line(out, &format!("PRODID:{}", PRODID))?;
+    write_line(out, "CALSCALE:GREGORIAN")?;
+    for worklog in archive.worklogs.iter().filter(|w| !w.ignored) {
+        let (stopped, taskobj) = match (&worklog.stopped, tasks.get(&worklog.task_id)) {
+            (Some(stopped), Some(taskobj)) => (stopped, taskobj),
+            _ => continue,
+        };
+        write_line(out, "BEGIN:VEVENT")?;
+        write_line(out, &format!("UID:worklog-{}@ttrackr", worklog.id))?;
+        write_line(out, &format!("DTSTAMP:{}", dtstamp))?;
+        write_line(out, &format!("DTSTART:{}", utc_to_ics(&worklog.started)))?;
+        write_line(out, &format!("DTEND:{}", utc_to_ics(stopped)))?;
+        write_line(out, &format!("SUMMARY:{}", escape_text(&taskobj.taskname)))?;
+        // notes of the session, falling back to the task's
+        let notes = worklog.notes.as_deref().or(taskobj.notes.as_deref());
+        if let Some(notes) = notes.filter(|n| !n.is_empty()) {
+            write_line(out, &format!("DESCRIPTION:{}", escape_text(notes)))?;
+        }
+        write_line(out, "END:VEVENT")?;
+    }
+    if todos {
+        for taskobj in archive.tasks.iter().filter(|t| t.deleted_at.is_none()) {
+            let duedate = match &taskobj.duedate {
+                Some(duedate) => NaiveDate::parse_from_str(duedate, "%Y-%m-%d").map_err(|_| {
+                    Error::InvalidTimestamp {
+                        value: duedate.to_owned(),
+                    }
+                })?,
+                None => continue,
+            };
+            write_line(out, "BEGIN:VTODO")?;
+            write_line(out, &format!("UID:task-{}@ttrackr", taskobj.id))?;
+            write_line(out, &format!("DTSTAMP:{}", dtstamp))?;
+            write_line(out, &format!("DUE;VALUE=DATE:{}", duedate.format("%Y%m%d")))?;
+            write_line(out, &format!("SUMMARY:{}", escape_text(&taskobj.taskname)))?;
+            if let Some(notes) = taskobj.notes.as_deref().filter(|n| !n.is_empty()) {
+                write_line(out, &format!("DESCRIPTION:{}", escape_text(notes)))?;
+            }
+            let status = if taskobj.done {
+                "COMPLETED"
+            } else {
+                "NEEDS-ACTION"
+            };
+            write_line(out, &format!("STATUS:{}", status))?;
+            write_line(out, "END:VTODO")?;
+        }
+    }
+    write_line(out, "END:VCALENDAR")?;
+    Ok(())
+}
+
+/// Format a UTC time as iCalendar UTC date-time.
+fn utc_to_ics(utc: &DateTime<Utc>) -> String {
+    utc.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a TEXT property value.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Write a content line terminated by CRLF, folded at `MAX_LINE` octets
+/// without splitting UTF-8 sequences.
+fn write_line(out: &mut impl Write, line: &str) -> Result<(), Error> {
+    let mut rest = line;
+    let mut limit = MAX_LINE;
+    while rest.len() > limit {
+        let mut end = limit;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        write!(out, "{}\r\n ", &rest[..end])?;
+        rest = &rest[end..];
+        // the leading space of a continuation line counts towards its length
+        limit = MAX_LINE - 1;
+    }
+    write!(out, "{}\r\n", rest)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::archive::ARCHIVE_VERSION;
+    use crate::utils::BoxError;
+    use chrono::TimeZone;
+
+    fn fold(line: &str) -> Result<String, BoxError> {
+        let mut buf = Vec::new();
+        write_line(&mut buf, line)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn folded_lines() -> Result<(), BoxError> {
+        assert_eq!(fold("SUMMARY:short")?, "SUMMARY:short\r\n");
+        let long = format!("DESCRIPTION:{}", "é".repeat(60));
+        let folded = fold(&long)?;
+        for line in folded.split("\r\n") {
+            assert!(line.len() <= MAX_LINE);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", long));
+        Ok(())
+    }
+
+    #[test]
+    fn escaped_text() {
+        assert_eq!(escape_text("a,b;c\\d\r\ne"), "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn calendar() -> Result<(), BoxError> {
+        let task = |id: i32, duedate: Option<&str>, done: bool| models::Task {
+            id,
+            created: Utc.ymd(2020, 5, 1).and_hms(8, 0, 0).into(),
+            taskname: format!("task{}", id),
+            notes: Some(String::from("notes, more")),
+            allocated: 0,
+            duedate: duedate.map(String::from),
+            done,
+            deleted_at: None,
+            recur: None,
+        };
+        let stopped = Utc.ymd(2020, 5, 10).and_hms(10, 0, 0);
+        let worklog = |id: i32, stopped: Option<DateTime<Utc>>, ignored: bool| models::Worklog {
+            id,
+            task_id: 1,
+            started: Utc.ymd(2020, 5, 10).and_hms(9, 0, 0).into(),
+            stopped: stopped.map(models::Timestamp),
+            duration: 0,
+            ignored,
+            notes: None,
+        };
+        let archive = Archive {
+            version: ARCHIVE_VERSION,
+            exported: String::from("2020-05-11T12:00:00Z"),
+            tasks: vec![task(1, Some("2020-05-20"), true), task(2, None, false)],
+            worklogs: vec![
+                worklog(1, Some(stopped), false),
+                worklog(2, Some(stopped), true),
+                worklog(3, None, false),
+            ],
+            pauses: Vec::new(),
+            tags: Vec::new(),
+            task_tags: Vec::new(),
+        };
+        let mut buf = Vec::new();
+        write_ics(&archive, true, &mut buf)?;
+        let content = String::from_utf8(buf)?;
+        assert!(content.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(content.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(content.matches("BEGIN:VEVENT").count(), 1);
+        assert!(content.contains("UID:worklog-1@ttrackr\r\n"));
+        assert!(content.contains("DTSTAMP:20200511T120000Z\r\n"));
+        assert!(content.contains("DTSTART:20200510T090000Z\r\nDTEND:20200510T100000Z\r\n"));
+        assert!(content.contains("DESCRIPTION:notes\\, more\r\n"));
+        assert_eq!(content.matches("BEGIN:VTODO").count(), 1);
+        assert!(content.contains("DUE;VALUE=DATE:20200520\r\n"));
+        assert!(content.contains("STATUS:COMPLETED\r\n"));
+        Ok(())
+    }
+}
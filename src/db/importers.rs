@@ -0,0 +1,177 @@
+// readers for exports of other time trackers
+use crate::error::Error;
+use crate::utils::{local_to_utc, parse_local_time};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_derive::Deserialize;
+use std::io::Read;
+
+/// Task name used when an entry has no tags or project.
+const UNTAGGED: &str = "untagged";
+
+/// A completed time entry read from another time tracker.
+#[derive(Debug, PartialEq)]
+pub struct ImportedEntry {
+    pub taskname: String,
+    pub started: DateTime<Utc>,
+    pub stopped: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+struct TimewarriorInterval {
+    start: String,
+    end: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Read the JSON array written by `timew export`.
+///
+/// Tags are joined into a `::` separated task name. Open intervals are skipped.
+pub fn read_timewarrior(input: impl Read) -> Result<Vec<ImportedEntry>, Error> {
+    let intervals: Vec<TimewarriorInterval> = serde_json::from_reader(input)?;
+    let mut entries = Vec::with_capacity(intervals.len());
+    for interval in intervals.iter() {
+        let end = match &interval.end {
+            Some(end) => end,
+            None => continue,
+        };
+        entries.push(ImportedEntry {
+            taskname: join_taskname(&interval.tags),
+            started: parse_timewarrior_time(&interval.start)?,
+            stopped: parse_timewarrior_time(end)?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Timewarrior writes UTC timestamps like `20200510T085841Z`.
+fn parse_timewarrior_time(text: &str) -> Result<DateTime<Utc>, Error> {
+    let dt = NaiveDateTime::parse_from_str(text, "%Y%m%dT%H%M%SZ").map_err(|_| {
+        Error::InvalidTimestamp {
+            value: text.to_owned(),
+        }
+    })?;
+    Ok(DateTime::from_utc(dt, Utc))
+}
+
+#[derive(Deserialize)]
+struct TogglRow {
+    #[serde(rename = "Project", default)]
+    project: String,
+    #[serde(rename = "Task", default)]
+    task: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "Start date")]
+    start_date: String,
+    #[serde(rename = "Start time")]
+    start_time: String,
+    #[serde(rename = "End date")]
+    end_date: String,
+    #[serde(rename = "End time")]
+    end_time: String,
+}
+
+/// Read Toggl's detailed report CSV export.
+///
+/// Project and task are joined into a `::` separated task name, falling back to
+/// the description. Toggl writes times in the user's timezone, which is assumed
+/// to be the local timezone.
+pub fn read_toggl(mut input: impl Read) -> Result<Vec<ImportedEntry>, Error> {
+    let mut content = String::new();
+    input.read_to_string(&mut content)?;
+    let content = content.trim_start_matches('\u{feff}');
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let mut entries = Vec::new();
+    for row in reader.deserialize() {
+        let row: TogglRow = row?;
+        let mut segments = vec![row.project, row.task];
+        segments.retain(|s| !s.trim().is_empty());
+        if segments.is_empty() {
+            segments.push(row.description);
+        }
+        entries.push(ImportedEntry {
+            taskname: join_taskname(&segments),
+            started: parse_toggl_time(&row.start_date, &row.start_time)?,
+            stopped: parse_toggl_time(&row.end_date, &row.end_time)?,
+        });
+    }
+    Ok(entries)
+}
+
+fn parse_toggl_time(date: &str, time: &str) -> Result<DateTime<Utc>, Error> {
+    local_to_utc(&parse_local_time(&format!("{} {}", date, time))?)
+}
+
+fn join_taskname(segments: &[String]) -> String {
+    let segments: Vec<&str> = segments
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if segments.is_empty() {
+        String::from(UNTAGGED)
+    } else {
+        segments.join("::")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::BoxError;
+    use chrono::TimeZone;
+
+    #[test]
+    fn timewarrior_export() -> Result<(), BoxError> {
+        let content = r#"[
+            {"id":3,"start":"20200510T085841Z","end":"20200510T095841Z","tags":["client","api"]},
+            {"id":2,"start":"20200510T100000Z","end":"20200510T103000Z"},
+            {"id":1,"start":"20200510T110000Z","tags":["open"]}
+        ]"#;
+        let entries = read_timewarrior(content.as_bytes())?;
+        assert_eq!(
+            entries,
+            vec![
+                ImportedEntry {
+                    taskname: String::from("client::api"),
+                    started: Utc.ymd(2020, 5, 10).and_hms(8, 58, 41),
+                    stopped: Utc.ymd(2020, 5, 10).and_hms(9, 58, 41),
+                },
+                ImportedEntry {
+                    taskname: String::from("untagged"),
+                    started: Utc.ymd(2020, 5, 10).and_hms(10, 0, 0),
+                    stopped: Utc.ymd(2020, 5, 10).and_hms(10, 30, 0),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn toggl_export() -> Result<(), BoxError> {
+        let content = "\u{feff}User,Email,Client,Project,Task,Description,Billable,\
+            Start date,Start time,End date,End time,Duration,Tags,Amount ()\n\
+            Me,me@example.com,ACME,Website,Design,mockups,No,\
+            2020-05-10,09:00:00,2020-05-10,10:30:00,01:30:00,,\n\
+            Me,me@example.com,,,,standup,No,\
+            2020-05-10,23:50:00,2020-05-11,00:05:00,00:15:00,,\n";
+        let entries = read_toggl(content.as_bytes())?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].taskname, "Website::Design");
+        assert_eq!(
+            entries[0].started,
+            parse_toggl_time("2020-05-10", "09:00:00")?
+        );
+        assert_eq!(
+            entries[0].stopped,
+            parse_toggl_time("2020-05-10", "10:30:00")?
+        );
+        assert_eq!(entries[1].taskname, "standup");
+        assert_eq!(
+            entries[1].stopped,
+            parse_toggl_time("2020-05-11", "00:05:00")?
+        );
+        Ok(())
+    }
+}
@@ -1,25 +1,40 @@
+pub mod archive;
+pub mod calendar;
+pub mod importers;
 pub mod models;
 pub mod ops;
 pub mod schema;
-pub mod utils;
 
 use crate::config::Config;
-use crate::utils::BoxError;
+use crate::error::Error;
 
+use diesel::connection::SimpleConnection;
 use diesel::prelude::*;
+use std::path::Path;
 
 embed_migrations!();
 
-pub fn get_connection(config: &Config) -> Result<SqliteConnection, BoxError> {
+pub fn get_connection(config: &Config) -> Result<SqliteConnection, Error> {
     establish_connection(config.database.get("path").unwrap())
 }
 
-pub fn establish_connection(dbpath: &str) -> Result<SqliteConnection, BoxError> {
-    let conn = SqliteConnection::establish(dbpath)
-        .unwrap_or_else(|_| panic!("Error connecting to {}", dbpath));
+pub fn establish_connection(dbpath: &str) -> Result<SqliteConnection, Error> {
+    let conn = SqliteConnection::establish(dbpath)?;
 
     // This will run the necessary migrations.
-    embedded_migrations::run_with_output(&conn, &mut std::io::stdout())?;
+    embedded_migrations::run(&conn)?;
 
     Ok(conn)
 }
+
+/// Open an existing database for reading only, without running migrations.
+///
+/// Returns `None` when the database file does not exist yet.
+pub fn establish_read_only(dbpath: &str) -> Result<Option<SqliteConnection>, Error> {
+    if !Path::new(dbpath).exists() {
+        return Ok(None);
+    }
+    let conn = SqliteConnection::establish(dbpath)?;
+    conn.batch_execute("PRAGMA query_only = ON")?;
+    Ok(Some(conn))
+}
@@ -1,15 +1,87 @@
 use super::schema::*;
+use crate::utils::{parse_timestamp, TIMESTAMP_FORMAT};
+use chrono::{DateTime, TimeZone, Utc};
+use diesel::deserialize::{self, FromSql};
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::BigInt;
+use diesel::sqlite::Sqlite;
+use serde_derive::{Deserialize, Serialize};
+use std::io::Write;
 
-#[derive(Identifiable, Debug, Queryable)]
+/// UTC time stored as Unix timestamp in seconds.
+///
+/// Serialized as `%Y-%m-%d %H:%M:%S` in UTC, the format used by archives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, AsExpression, FromSqlRow)]
+#[sql_type = "BigInt"]
+pub struct Timestamp(pub DateTime<Utc>);
+
+impl Timestamp {
+    pub fn from_unix(seconds: i64) -> Self {
+        Timestamp(Utc.timestamp(seconds, 0))
+    }
+
+    pub fn unix(&self) -> i64 {
+        self.0.timestamp()
+    }
+}
+
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(dt: DateTime<Utc>) -> Self {
+        Timestamp(dt)
+    }
+}
+
+impl std::ops::Deref for Timestamp {
+    type Target = DateTime<Utc>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl ToSql<BigInt, Sqlite> for Timestamp {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Sqlite>) -> serialize::Result {
+        ToSql::<BigInt, Sqlite>::to_sql(&self.unix(), out)
+    }
+}
+
+impl FromSql<BigInt, Sqlite> for Timestamp {
+    fn from_sql(
+        value: Option<&<Sqlite as diesel::backend::Backend>::RawValue>,
+    ) -> deserialize::Result<Self> {
+        let seconds = <i64 as FromSql<BigInt, Sqlite>>::from_sql(value)?;
+        Ok(Timestamp::from_unix(seconds))
+    }
+}
+
+impl serde::Serialize for Timestamp {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0.format(TIMESTAMP_FORMAT).to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Timestamp {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <String as serde::Deserialize>::deserialize(deserializer)?;
+        let naive = parse_timestamp(&text).map_err(serde::de::Error::custom)?;
+        Ok(Timestamp(DateTime::from_utc(naive, Utc)))
+    }
+}
+
+#[derive(Identifiable, Debug, Queryable, QueryableByName, Serialize, Deserialize)]
 #[table_name = "task"]
 pub struct Task {
     pub id: i32,
-    pub created: String,
+    pub created: Timestamp,
     pub taskname: String,
     pub notes: Option<String>,
     pub allocated: i32,
     pub duedate: Option<String>,
     pub done: bool,
+    #[serde(default)] // missing in archives of older versions
+    pub deleted_at: Option<Timestamp>, // moved to the trash
+    #[serde(default)]
+    pub recur: Option<String>, // recurrence rule, see `Recurrence`
 }
 
 impl Task {
@@ -25,19 +97,47 @@ impl Task {
         UpdateTask {
             id: self.id,
             notes,
-            allocated: self.allocated,
+            allocated: Some(self.allocated),
             duedate,
             done: Some(self.done),
         }
     }
 }
 
+/// Task with time summed over its non-ignored worklogs.
+#[derive(Debug, QueryableByName)]
+pub struct TaskSpent {
+    #[diesel(embed)]
+    pub task: Task,
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub spent: i32, // including the running worklog
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub current_spent: i32, // running worklog only
+}
+
+/// Running worklog with its task name and times up to now.
+#[derive(Debug, QueryableByName)]
+pub struct RunningWorklog {
+    #[sql_type = "diesel::sql_types::Text"]
+    pub taskname: String,
+    #[sql_type = "diesel::sql_types::BigInt"]
+    pub started: Timestamp,
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub spent: i32, // of the task, including this worklog
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub current_spent: i32, // without breaks
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub current_wall: i32, // including breaks
+    #[sql_type = "diesel::sql_types::Nullable<diesel::sql_types::BigInt>"]
+    pub paused_at: Option<Timestamp>, // start of the open break
+}
+
 #[derive(Debug, AsChangeset, Identifiable)]
 #[table_name = "task"]
 pub struct UpdateTask {
     pub id: i32,
     pub notes: Option<String>,
-    pub allocated: i32,
+    pub allocated: Option<i32>,
     pub duedate: Option<String>,
     pub done: Option<bool>,
 }
@@ -49,22 +149,114 @@ pub struct NewTask<'a> {
     pub notes: Option<&'a str>,
     pub allocated: Option<i32>,
     pub duedate: Option<&'a str>,
+    pub created: Option<Timestamp>,
+    pub done: Option<bool>,
+    pub deleted_at: Option<Timestamp>,
+    pub recur: Option<&'a str>,
 }
 
-#[derive(Identifiable, Associations, Debug, Queryable)]
+#[derive(Identifiable, Associations, Debug, Queryable, Serialize, Deserialize)]
 #[belongs_to(Task)]
 #[table_name = "worklog"]
 pub struct Worklog {
     pub id: i32,
     pub task_id: i32,
-    pub started: String,
-    pub stopped: Option<String>,
+    pub started: Timestamp,
+    pub stopped: Option<Timestamp>,
     pub duration: i32,
     pub ignored: bool,
+    #[serde(default)] // missing in archives of older versions
+    pub notes: Option<String>, // what was done in the session
 }
 
 #[derive(Debug, Insertable, Default)]
 #[table_name = "worklog"]
-pub struct NewWorklog {
+pub struct NewWorklog<'a> {
+    pub task_id: i32,
+    pub started: Option<Timestamp>,
+    pub stopped: Option<Timestamp>,
+    pub duration: Option<i32>,
+    pub ignored: Option<bool>,
+    pub notes: Option<&'a str>,
+}
+
+/// Break inside a worklog, `stopped` is `None` while the task is paused.
+#[derive(Identifiable, Associations, Debug, Queryable, Serialize, Deserialize)]
+#[belongs_to(Worklog)]
+#[table_name = "pause"]
+pub struct Pause {
+    pub id: i32,
+    pub worklog_id: i32,
+    pub started: Timestamp,
+    pub stopped: Option<Timestamp>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "pause"]
+pub struct NewPause {
+    pub worklog_id: i32,
+    pub started: Timestamp,
+}
+
+#[derive(Identifiable, Debug, Queryable, QueryableByName, Serialize, Deserialize)]
+#[table_name = "tag"]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Tag with the number of its tasks and time summed over their worklogs.
+#[derive(Debug, QueryableByName)]
+pub struct TagSpent {
+    #[diesel(embed)]
+    pub tag: Tag,
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub tasks: i32,
+    #[sql_type = "diesel::sql_types::Integer"]
+    pub spent: i32, // including running worklogs
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "tag"]
+pub struct NewTag<'a> {
+    pub name: &'a str,
+}
+
+#[derive(Debug, Insertable, Queryable, Serialize, Deserialize)]
+#[table_name = "task_tag"]
+pub struct TaskTag {
     pub task_id: i32,
+    pub tag_id: i32,
+}
+
+/// Command whose changes are kept in the undo log, `open` while it runs.
+#[derive(Identifiable, Debug, Queryable)]
+#[table_name = "undo_op"]
+pub struct UndoOp {
+    pub id: i32,
+    pub created: Timestamp,
+    pub command: String,
+    pub open: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "undo_op"]
+pub struct NewUndoOp<'a> {
+    pub created: Timestamp,
+    pub command: &'a str,
+}
+
+/// Row change logged by database triggers while an operation is open.
+///
+/// Row images are JSON objects of all columns, `before` is `None` for inserted
+/// rows and `after` is `None` for deleted ones.
+#[derive(Identifiable, Associations, Debug, Queryable)]
+#[belongs_to(UndoOp, foreign_key = "op_id")]
+#[table_name = "undo_log"]
+pub struct UndoLog {
+    pub id: i32,
+    pub op_id: i32,
+    pub tablename: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
 }
@@ -1,257 +1,1850 @@
-use super::{get_connection, models, schema, BoxError, Config};
-use crate::utils::get_timestamp;
-use ansi_term::Style;
-use chrono::{NaiveDateTime, Utc};
+use super::archive::{Archive, ImportSummary, ARCHIVE_VERSION};
+use super::importers::ImportedEntry;
+use super::models::Timestamp;
+use super::{establish_connection, establish_read_only, get_connection, models, schema, Config};
+use crate::error::Error;
+use crate::recurrence::Recurrence;
+use crate::utils::{
+    get_timestamp, local_date_to_utc, seconds_between, split_by_day, utc_to_iso8601,
+};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use diesel::prelude::*;
 use log::debug;
-use std::convert::TryFrom;
+use serde_derive::Serialize;
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
 // use std::io::{stdin, Read};
 
-pub fn create_task(
-    config: &Config,
-    taskname: &str,
-    notes: Option<&str>,
-    allocated: Option<i32>,
-    duedate: Option<&str>,
-) -> Result<(), BoxError> {
-    let conn = get_connection(config)?;
-    let new_task = models::NewTask {
-        taskname,
-        notes,
-        allocated,
-        duedate,
-    };
-
-    let result = diesel::insert_into(schema::task::table)
-        .values(&new_task)
-        .execute(&conn);
-    match result {
-        Ok(_val) => {
-            println!("{} created.", Style::new().bold().paint(taskname));
-            Ok(())
+/// Task listing entry with time rolled up from its `::` separated subtasks.
+#[derive(Debug)]
+pub struct TaskTreeNode {
+    pub name: String, // full task name
+    pub depth: usize,
+    pub task: Option<models::Task>, // `None` for parents that only exist as a prefix
+    pub own_spent: i32,
+    pub spent: i32, // own and all descendants
+    pub own_allocated: i32,
+    pub allocated: i32, // own and all descendants
+}
+
+impl TaskTreeNode {
+    /// Last segment of the task name.
+    pub fn label(&self) -> &str {
+        self.name.rsplit("::").next().unwrap_or(&self.name)
+    }
+}
+
+/// Arrange tasks and their spent time into a depth first ordered tree.
+///
+/// Missing parents are added as nodes without a task.
+pub fn build_task_tree(rows: Vec<(models::Task, i32)>) -> Vec<TaskTreeNode> {
+    let mut nodes: BTreeMap<Vec<String>, TaskTreeNode> = BTreeMap::new();
+    for (taskobj, spent) in rows.into_iter() {
+        let segments: Vec<String> = taskobj.taskname.split("::").map(String::from).collect();
+        for depth in 0..segments.len() {
+            let key = segments[..=depth].to_vec();
+            let node = nodes.entry(key.clone()).or_insert_with(|| TaskTreeNode {
+                name: key.join("::"),
+                depth,
+                task: None,
+                own_spent: 0,
+                spent: 0,
+                own_allocated: 0,
+                allocated: 0,
+            });
+            node.spent += spent;
+            node.allocated += taskobj.allocated;
+        }
+        let node = nodes.get_mut(&segments).unwrap();
+        node.own_spent = spent;
+        node.own_allocated = taskobj.allocated;
+        node.task = Some(taskobj);
+    }
+    nodes.into_values().collect()
+}
+
+/// Task started or stopped by `start_worklogs`, `stop_worklogs` or `switch_worklog`.
+#[derive(Debug)]
+pub struct WorklogEvent {
+    pub taskname: String,
+    pub timestamp: DateTime<Utc>,
+    pub completed: bool, // flagged done by `autodone` when stopped
+}
+
+/// Tasks stopped and started by `switch_worklog`.
+#[derive(Debug)]
+pub struct SwitchEvent {
+    pub stopped: Vec<WorklogEvent>,
+    pub started: Option<WorklogEvent>, // `None` when the task was already running
+}
+
+/// Notes of the task left by `merge_tasks`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeNotes {
+    Keep,   // of the target
+    Source, // of the source, or the target's when the source has none
+    Append, // of the target followed by those of the source
+}
+
+impl std::str::FromStr for MergeNotes {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_ref() {
+            "keep" => Ok(MergeNotes::Keep),
+            "source" => Ok(MergeNotes::Source),
+            "append" => Ok(MergeNotes::Append),
+            _ => Err("no match"),
+        }
+    }
+}
+
+/// Allocated time of the task left by `merge_tasks`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeAllocation {
+    Keep,
+    Source,
+    Sum,
+    Max,
+}
+
+impl std::str::FromStr for MergeAllocation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_ref() {
+            "keep" => Ok(MergeAllocation::Keep),
+            "source" => Ok(MergeAllocation::Source),
+            "sum" => Ok(MergeAllocation::Sum),
+            "max" => Ok(MergeAllocation::Max),
+            _ => Err("no match"),
+        }
+    }
+}
+
+/// Result of `merge_tasks`.
+#[derive(Debug)]
+pub struct MergeEvent {
+    pub task: models::Task,            // target after the merge
+    pub worklogs: usize,               // moved from the source
+    pub adjusted: usize,               // moved worklogs cut short or ignored
+    pub stopped: Option<WorklogEvent>, // source stopped by `force`
+}
+
+#[derive(Debug)]
+pub struct ReportEntry {
+    pub date: NaiveDate, // local date
+    pub taskname: String,
+    pub spent: i32,         // without breaks
+    pub breaks: i32,        // paused time of the sessions
+    pub notes: Vec<String>, // of the sessions in order
+}
+
+/// Serialized with times in ISO 8601 UTC.
+#[derive(Debug, Serialize)]
+pub struct RunningTask {
+    pub name: String,
+    pub spent: i32,         // total spent
+    pub current_spent: i32, // current session without breaks
+    pub current_wall: i32,  // current session including breaks
+    #[serde(serialize_with = "serialize_iso8601")]
+    pub started: DateTime<Utc>, // last started
+    #[serde(serialize_with = "serialize_iso8601_opt")]
+    pub paused_at: Option<DateTime<Utc>>, // start of the break the session is on
+}
+
+fn serialize_iso8601<S: serde::Serializer>(
+    time: &DateTime<Utc>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&utc_to_iso8601(time))
+}
+
+fn serialize_iso8601_opt<S: serde::Serializer>(
+    time: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match time {
+        Some(time) => serialize_iso8601(time, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Row change of an operation in the undo log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn label(&self) -> &str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Deleted => "deleted",
         }
-        Err(err) => Err(err.into()),
     }
 }
 
-pub fn list_tasks(
-    config: &Config,
-    taskfilter: Option<&str>,
-    status: Option<&str>,
-) -> Result<Vec<models::Task>, BoxError> {
-    use schema::task::dsl::*;
-    let conn = get_connection(config)?;
-    // let query = task.load::<models::Task>(&conn);
-    let mut query = task.into_boxed();
-    if let Some(taskfilter) = taskfilter {
-        let mut parent = String::from(taskfilter);
-        parent.push_str("::%");
-        query = query.filter(taskname.like(taskfilter).or(taskname.like(parent)));
-    }
-    if let Some(status) = status {
-        match status {
-            "done" => {
-                query = query.filter(done.eq(true));
+#[derive(Debug)]
+pub struct UndoChange {
+    pub table: String,
+    pub key: String, // `#id`, or key columns with values for `task_tag`
+    pub kind: ChangeKind,
+    pub columns: Vec<String>, // changed columns of updated rows
+}
+
+/// Operation recorded in the undo log with the rows it changed in order.
+#[derive(Debug)]
+pub struct UndoOperation {
+    pub id: i32,
+    pub created: DateTime<Utc>,
+    pub command: String,
+    pub changes: Vec<UndoChange>,
+}
+
+/// Number of latest operations kept in the undo log.
+const UNDO_HISTORY: i64 = 100;
+
+/// Tables logged by the undo triggers with their key columns.
+const UNDO_TABLES: [(&str, &[&str]); 5] = [
+    ("task", &["id"]),
+    ("worklog", &["id"]),
+    ("pause", &["id"]),
+    ("tag", &["id"]),
+    ("task_tag", &["task_id", "tag_id"]),
+];
+
+/// Handle to a ttrackr database.
+///
+/// Opens the connection and runs pending migrations once, all task and worklog
+/// operations are methods sharing that connection.
+pub struct Tracker {
+    conn: SqliteConnection,
+    autodone: bool,             // complete tasks when allocated time is spent
+    single_active: bool,        // allow only one running task
+    undo_op: Cell<Option<i32>>, // operation being recorded by `record`
+}
+
+impl Tracker {
+    /// Open the database configured in `config`.
+    pub fn new(config: &Config) -> Result<Self, Error> {
+        Ok(Tracker {
+            conn: get_connection(config)?,
+            autodone: config.autodone,
+            single_active: config.single_active,
+            undo_op: Cell::new(None),
+        })
+    }
+
+    /// Open the database at `dbpath`, creating it if missing.
+    pub fn open(dbpath: &str) -> Result<Self, Error> {
+        Ok(Tracker {
+            conn: establish_connection(dbpath)?,
+            autodone: false,
+            single_active: false,
+            undo_op: Cell::new(None),
+        })
+    }
+
+    /// Open the database at `dbpath` for reading only, `None` when it does not exist.
+    ///
+    /// Pending migrations are not run, so this is quick but only meant for reading
+    /// a database that was opened by `open` or `new` before.
+    pub fn open_read_only(dbpath: &str) -> Result<Option<Self>, Error> {
+        Ok(establish_read_only(dbpath)?.map(|conn| Tracker {
+            conn,
+            autodone: false,
+            single_active: false,
+            undo_op: Cell::new(None),
+        }))
+    }
+
+    /// Open a fresh database that only lives as long as the tracker.
+    pub fn in_memory() -> Result<Self, Error> {
+        Self::open(":memory:")
+    }
+
+    /// Flag tasks done when stopping them after their allocated time is spent.
+    pub fn set_autodone(&mut self, value: bool) {
+        self.autodone = value;
+    }
+
+    /// Refuse to start a task while another one is running, see `switch_worklog`.
+    pub fn set_single_active(&mut self, value: bool) {
+        self.single_active = value;
+    }
+
+    pub fn is_single_active(&self) -> bool {
+        self.single_active
+    }
+
+    /// Run `f` as operation `command` of the undo log.
+    ///
+    /// Rows changed while the operation is open are logged by database triggers.
+    /// Operations recorded by `f` join this one, so a command changing data through
+    /// several calls is undone at once. Operations that change nothing are dropped.
+    pub fn record<T, E: From<Error>>(
+        &self,
+        command: &str,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        if self.undo_op.get().is_some() {
+            return f();
+        }
+        let opid = helper::open_undo_op(&self.conn, command)?;
+        self.undo_op.set(Some(opid));
+        let result = f();
+        self.undo_op.set(None);
+        // an error of `f` takes precedence over one closing the operation
+        let closed = helper::close_undo_op(&self.conn, opid);
+        let value = result?;
+        closed?;
+        Ok(value)
+    }
+
+    /// List the latest `limit` operations of the undo log, latest first.
+    pub fn history(&self, limit: Option<i64>) -> Result<Vec<UndoOperation>, Error> {
+        let rows = helper::load_undo_ops(&self.conn, limit)?;
+        rows.iter()
+            .map(|(op, logs)| helper::describe_undo_op(op, logs))
+            .collect()
+    }
+
+    /// Revert the latest `count` operations of the undo log in a single transaction.
+    ///
+    /// Returns the reverted operations, latest first, which are dropped from the log.
+    pub fn undo(&self, count: i64) -> Result<Vec<UndoOperation>, Error> {
+        use schema::{undo_log, undo_op};
+        let conn = &self.conn;
+        helper::in_transaction(conn, false, || {
+            // nothing is logged while reverting
+            diesel::update(undo_op::table.filter(undo_op::open.eq(true)))
+                .set(undo_op::open.eq(false))
+                .execute(conn)?;
+            let rows = helper::load_undo_ops(conn, Some(count))?;
+            let mut reverted = Vec::with_capacity(rows.len());
+            for (op, logs) in rows.iter() {
+                for log in logs.iter().rev() {
+                    helper::revert_change(conn, log)?;
+                }
+                diesel::delete(undo_log::table.filter(undo_log::op_id.eq(op.id))).execute(conn)?;
+                diesel::delete(op).execute(conn)?;
+                reverted.push(helper::describe_undo_op(op, logs)?);
             }
-            "incomplete" => {
-                query = query.filter(done.eq(false));
+            Ok(reverted)
+        })
+    }
+
+    pub fn create_task(
+        &self,
+        taskname: &str,
+        notes: Option<&str>,
+        allocated: Option<i32>,
+        duedate: Option<&str>,
+    ) -> Result<models::Task, Error> {
+        self.record(&format!("create {}", taskname), || {
+            let conn = &self.conn;
+            if let Some(existing) = helper::find_task(conn, taskname)? {
+                if existing.deleted_at.is_some() {
+                    return Err(Error::TaskIsTrashed {
+                        taskname: existing.taskname,
+                    });
+                }
+                return Err(Error::TaskAlreadyExists {
+                    taskname: taskname.to_owned(),
+                });
             }
-            _ => (),
+            let new_task = models::NewTask {
+                taskname,
+                notes,
+                allocated,
+                duedate,
+                ..Default::default()
+            };
+
+            diesel::insert_into(schema::task::table)
+                .values(&new_task)
+                .execute(conn)?;
+            helper::get_task(conn, taskname)
+        })
+    }
+
+    /// List tasks matching `taskfilter`, `status` and all of `tags`, tasks in the
+    /// trash are left out.
+    ///
+    /// `taskfilter` matches a task name and its `::` separated subtasks, `status` is
+    /// one of `done`, `incomplete` or `all`.
+    pub fn list_tasks(
+        &self,
+        taskfilter: Option<&str>,
+        status: Option<&str>,
+        tags: &[String],
+    ) -> Result<Vec<models::Task>, Error> {
+        use schema::task::dsl::*;
+        let conn = &self.conn;
+        // let query = task.load::<models::Task>(conn);
+        let mut query = task.filter(deleted_at.is_null()).into_boxed();
+        if let Some(taskfilter) = taskfilter {
+            let mut parent = String::from(taskfilter);
+            parent.push_str("::%");
+            query = query.filter(taskname.like(taskfilter).or(taskname.like(parent)));
+        }
+        if let Some(status) = status {
+            match status {
+                "done" => {
+                    query = query.filter(done.eq(true));
+                }
+                "incomplete" => {
+                    query = query.filter(done.eq(false));
+                }
+                _ => (),
+            };
+        }
+        if let Some(ids) = helper::get_tagged_task_ids(conn, tags)? {
+            query = query.filter(id.eq_any(ids));
+        }
+        let data = query.load::<models::Task>(conn);
+        match data {
+            Ok(val) => Ok(val),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// List tasks along with their spent time in a single query.
+    ///
+    /// Takes the same filters as `list_tasks`, running worklogs count up to now.
+    pub fn list_tasks_with_spent(
+        &self,
+        taskfilter: Option<&str>,
+        status: Option<&str>,
+        tags: &[String],
+    ) -> Result<Vec<models::TaskSpent>, Error> {
+        let done = match status {
+            Some("done") => Some(true),
+            Some("incomplete") => Some(false),
+            _ => None,
         };
+        let mut rows = helper::get_tasks_with_spent(&self.conn, taskfilter, done, None, false)?;
+        if let Some(ids) = helper::get_tagged_task_ids(&self.conn, tags)? {
+            rows.retain(|row| ids.contains(&row.task.id));
+        }
+        Ok(rows)
     }
-    let data = query.load::<models::Task>(&conn);
-    match data {
-        Ok(val) => Ok(val),
-        Err(err) => Err(err.into()),
+
+    /// List tasks as a tree, ordered depth first.
+    ///
+    /// Takes the same filters as `list_tasks`.
+    pub fn get_task_tree(
+        &self,
+        taskfilter: Option<&str>,
+        status: Option<&str>,
+        tags: &[String],
+    ) -> Result<Vec<TaskTreeNode>, Error> {
+        let rows = self
+            .list_tasks_with_spent(taskfilter, status, tags)?
+            .into_iter()
+            .map(|row| (row.task, row.spent))
+            .collect();
+        Ok(build_task_tree(rows))
     }
-}
 
-pub fn update_tasks(
-    config: &Config,
-    name: &str,
-    notes: Option<&str>,
-    allocated: Option<i32>,
-    duedate: Option<&str>,
-    done: Option<bool>,
-) -> Result<(), BoxError> {
-    let conn = get_connection(config)?;
-    let taskobj = helper::get_task(&conn, name)?;
-    let updatetask = models::UpdateTask {
-        id: taskobj.id,
-        notes: notes.map(String::from),
-        allocated: allocated.unwrap_or(0),
-        duedate: duedate.map(String::from),
-        done,
-    };
-
-    if let Some(d) = done {
-        if d {
-            // stop task is running
-            if helper::check_task_is_running(&conn, &taskobj)? {
-                helper::stop_worklog(&conn, &taskobj)?;
-            }
+    pub fn update_tasks(
+        &self,
+        name: &str,
+        notes: Option<&str>,
+        allocated: Option<i32>,
+        duedate: Option<&str>,
+        done: Option<bool>,
+    ) -> Result<models::Task, Error> {
+        self.record(&format!("edit {}", name), || {
+            let conn = &self.conn;
+            helper::in_transaction(conn, false, || {
+                let taskobj = helper::get_task(conn, name)?;
+                let updatetask = models::UpdateTask {
+                    id: taskobj.id,
+                    notes: notes.map(String::from),
+                    allocated,
+                    duedate: duedate.map(String::from),
+                    done,
+                };
+
+                if let Some(d) = done {
+                    if d {
+                        // stop task is running
+                        if helper::check_task_is_running(conn, &taskobj)? {
+                            helper::stop_worklog(conn, &taskobj, None, None)?;
+                        }
+                    }
+                }
+
+                let changed = updatetask.notes.is_some()
+                    || updatetask.allocated.is_some()
+                    || updatetask.duedate.is_some()
+                    || updatetask.done.is_some();
+                if changed {
+                    diesel::update(&updatetask).set(&updatetask).execute(conn)?;
+                }
+                if done == Some(true) && !taskobj.done {
+                    // renewed from the updated task, with its new notes and due date
+                    let updated = helper::get_task_by_id(conn, taskobj.id)?;
+                    helper::renew_recurring(conn, &updated)?;
+                }
+                helper::get_task_by_id(conn, taskobj.id)
+            })
+        })
+    }
+
+    /// Set recurrence rule of task `name`, `None` makes it a one-off task again.
+    ///
+    /// A bare monthly rule is pinned to the day of the due date, or of today
+    /// when the task has none.
+    pub fn set_recurrence(
+        &self,
+        name: &str,
+        rule: Option<&Recurrence>,
+    ) -> Result<models::Task, Error> {
+        self.record(&format!("recur {}", name), || {
+            use schema::task::dsl::*;
+            let conn = &self.conn;
+            let taskobj = helper::get_task(conn, name)?;
+            let due = helper::get_due_date(&taskobj)?;
+            diesel::update(&taskobj)
+                .set(recur.eq(rule.map(|r| r.pinned(due).to_string())))
+                .execute(conn)?;
+            helper::get_task_by_id(conn, taskobj.id)
+        })
+    }
+
+    /// List tasks with a recurrence rule, ordered by name. Tasks in the trash
+    /// are left out.
+    pub fn list_recurring(&self) -> Result<Vec<models::Task>, Error> {
+        use schema::task::dsl::*;
+        let tasks = task
+            .filter(recur.is_not_null())
+            .filter(deleted_at.is_null())
+            .order(taskname.asc())
+            .load::<models::Task>(&self.conn)?;
+        Ok(tasks)
+    }
+
+    /// Rename task `old` to `new`, with `recursive` also moving its subtasks
+    /// `old::*` under `new`.
+    ///
+    /// Returns the old and new names of the renamed tasks. Nothing is changed
+    /// when a new name is taken by a task that is not renamed.
+    pub fn rename_task(
+        &self,
+        old: &str,
+        new: &str,
+        recursive: bool,
+    ) -> Result<Vec<(String, String)>, Error> {
+        self.record(&format!("rename {} {}", old, new), || {
+            use schema::task::dsl::*;
+            let conn = &self.conn;
+            helper::in_transaction(conn, false, || {
+                let mut tasks = vec![helper::get_task(conn, old)?];
+                if recursive {
+                    let prefix = format!("{}::", old);
+                    let subtasks = task
+                        .filter(taskname.like(format!("{}%", prefix)))
+                        .order(taskname.asc())
+                        .load::<models::Task>(conn)?;
+                    // LIKE ignores case and treats `_` as a wildcard
+                    tasks.extend(
+                        subtasks
+                            .into_iter()
+                            .filter(|t| t.taskname.starts_with(&prefix)),
+                    );
+                }
+                let renamed: Vec<(i32, String, String)> = tasks
+                    .into_iter()
+                    .map(|t| {
+                        let newname = format!("{}{}", new, &t.taskname[old.len()..]);
+                        (t.id, t.taskname, newname)
+                    })
+                    .collect();
+                for (_, _, newname) in renamed.iter() {
+                    if let Some(found) = helper::find_task(conn, newname)? {
+                        if !renamed.iter().any(|(taskid, _, _)| *taskid == found.id) {
+                            return Err(Error::TaskAlreadyExists {
+                                taskname: newname.to_owned(),
+                            });
+                        }
+                    }
+                }
+                // move to temporary names first, the new names may still be in use
+                // by other renamed tasks
+                for (taskid, _, _) in renamed.iter() {
+                    diesel::update(task.find(taskid))
+                        .set(taskname.eq(format!("\0rename\0{}", taskid)))
+                        .execute(conn)?;
+                }
+                for (taskid, _, newname) in renamed.iter() {
+                    diesel::update(task.find(taskid))
+                        .set(taskname.eq(newname))
+                        .execute(conn)?;
+                }
+                Ok(renamed
+                    .into_iter()
+                    .map(|(_, oldname, newname)| (oldname, newname))
+                    .collect())
+            })
+        })
+    }
+
+    /// Move the worklogs and tags of task `source` to task `target` and delete
+    /// `source`.
+    ///
+    /// `notes` and `allocation` select what the target keeps, a missing due date
+    /// is taken from the source. Fails when both tasks are running, unless
+    /// `force` stops the source first. Worklogs of the source overlapping those of
+    /// the target are adjusted by `helper::fit_worklogs`, so no time is counted
+    /// twice. Nothing is changed when any step fails.
+    pub fn merge_tasks(
+        &self,
+        source: &str,
+        target: &str,
+        notes: MergeNotes,
+        allocation: MergeAllocation,
+        force: bool,
+    ) -> Result<MergeEvent, Error> {
+        self.record(&format!("merge {} {}", source, target), || {
+            use schema::{task_tag, worklog};
+            let conn = &self.conn;
+            helper::in_transaction(conn, false, || {
+                let sourcetask = helper::get_task(conn, source)?;
+                let targettask = helper::get_task(conn, target)?;
+                if sourcetask.id == targettask.id {
+                    return Err(Error::MergeIntoItself {
+                        taskname: targettask.taskname,
+                    });
+                }
+
+                let mut stopped = None;
+                if helper::check_task_is_running(conn, &sourcetask)?
+                    && helper::check_task_is_running(conn, &targettask)?
+                {
+                    if !force {
+                        return Err(Error::TasksAreRunning {
+                            source: sourcetask.taskname,
+                            target: targettask.taskname,
+                        });
+                    }
+                    let timestamp = get_timestamp();
+                    helper::stop_worklog(conn, &sourcetask, Some(timestamp.into()), None)?;
+                    stopped = Some(WorklogEvent {
+                        taskname: sourcetask.taskname.to_owned(),
+                        timestamp,
+                        completed: false,
+                    });
+                }
+
+                let adjusted = helper::fit_worklogs(conn, &sourcetask, &targettask)?;
+                let worklogs =
+                    diesel::update(worklog::table.filter(worklog::task_id.eq(sourcetask.id)))
+                        .set(worklog::task_id.eq(targettask.id))
+                        .execute(conn)?;
+                let tagids = task_tag::table
+                    .filter(task_tag::task_id.eq(sourcetask.id))
+                    .select(task_tag::tag_id)
+                    .load::<i32>(conn)?;
+                for tagid in tagids {
+                    let link = models::TaskTag {
+                        task_id: targettask.id,
+                        tag_id: tagid,
+                    };
+                    diesel::replace_into(task_tag::table)
+                        .values(&link)
+                        .execute(conn)?;
+                }
+                helper::delete_task_tags(conn, sourcetask.id)?;
+                diesel::delete(&sourcetask).execute(conn)?;
+
+                let notes = match (notes, &sourcetask.notes, &targettask.notes) {
+                    (MergeNotes::Source, Some(n), _) => Some(n.to_owned()),
+                    (MergeNotes::Append, Some(n), Some(t)) if !n.is_empty() && !t.is_empty() => {
+                        Some(format!("{}\n{}", t, n))
+                    }
+                    (MergeNotes::Append, Some(n), _) if !n.is_empty() => Some(n.to_owned()),
+                    _ => None,
+                };
+                let allocated = match allocation {
+                    MergeAllocation::Keep => None,
+                    MergeAllocation::Source => Some(sourcetask.allocated),
+                    MergeAllocation::Sum => Some(targettask.allocated + sourcetask.allocated),
+                    MergeAllocation::Max => Some(targettask.allocated.max(sourcetask.allocated)),
+                };
+                let duedate = match targettask.duedate {
+                    Some(_) => None,
+                    None => sourcetask.duedate,
+                };
+                let updatetask = models::UpdateTask {
+                    id: targettask.id,
+                    notes,
+                    allocated,
+                    duedate,
+                    done: None,
+                };
+                if updatetask.notes.is_some()
+                    || updatetask.allocated.is_some()
+                    || updatetask.duedate.is_some()
+                {
+                    diesel::update(&updatetask).set(&updatetask).execute(conn)?;
+                }
+                Ok(MergeEvent {
+                    task: helper::get_task_by_id(conn, targettask.id)?,
+                    worklogs,
+                    adjusted,
+                    stopped,
+                })
+            })
+        })
+    }
+
+    /// Move task `name` to the trash, stopping it when running.
+    ///
+    /// Its worklogs and tags are kept until the trash is purged.
+    pub fn delete_task(&self, name: &str) -> Result<Option<WorklogEvent>, Error> {
+        self.record(&format!("delete {}", name), || {
+            use schema::task::dsl::*;
+            let conn = &self.conn;
+            let timestamp = get_timestamp();
+            helper::in_transaction(conn, false, || {
+                let taskobj = helper::get_task(conn, name)?;
+                let mut stopped = None;
+                if helper::check_task_is_running(conn, &taskobj)? {
+                    helper::stop_worklog(conn, &taskobj, Some(timestamp.into()), None)?;
+                    stopped = Some(WorklogEvent {
+                        taskname: taskobj.taskname.to_owned(),
+                        timestamp,
+                        completed: false,
+                    });
+                }
+                diesel::update(&taskobj)
+                    .set(deleted_at.eq(Timestamp(timestamp)))
+                    .execute(conn)?;
+                Ok(stopped)
+            })
+        })
+    }
+
+    /// List tasks in the trash with their spent time, most recently deleted first.
+    pub fn list_trash(&self) -> Result<Vec<models::TaskSpent>, Error> {
+        let mut rows = helper::get_tasks_with_spent(&self.conn, None, None, None, true)?;
+        rows.sort_by_key(|row| std::cmp::Reverse(row.task.deleted_at));
+        Ok(rows)
+    }
+
+    /// Take task `name` out of the trash.
+    pub fn restore_task(&self, name: &str) -> Result<models::Task, Error> {
+        self.record(&format!("trash restore {}", name), || {
+            use schema::task::dsl::*;
+            let conn = &self.conn;
+            let taskobj = match helper::find_task(conn, name)? {
+                Some(taskobj) if taskobj.deleted_at.is_some() => taskobj,
+                Some(taskobj) => {
+                    return Err(Error::TaskIsNotTrashed {
+                        taskname: taskobj.taskname,
+                    })
+                }
+                None => {
+                    return Err(Error::TaskNotFound {
+                        taskname: name.to_owned(),
+                    })
+                }
+            };
+            diesel::update(&taskobj)
+                .set(deleted_at.eq(None::<Timestamp>))
+                .execute(conn)?;
+            helper::get_task_by_id(conn, taskobj.id)
+        })
+    }
+
+    /// Permanently delete tasks in the trash along with their worklogs and tags.
+    ///
+    /// With `older_than` only tasks deleted before that time are purged. Returns
+    /// names of the purged tasks.
+    pub fn purge_trash(&self, older_than: Option<DateTime<Utc>>) -> Result<Vec<String>, Error> {
+        self.record("trash purge", || {
+            use schema::task::dsl::*;
+            let conn = &self.conn;
+            helper::in_transaction(conn, false, || {
+                let mut query = task.filter(deleted_at.is_not_null()).into_boxed();
+                if let Some(older_than) = older_than {
+                    query = query.filter(deleted_at.lt(Timestamp(older_than)));
+                }
+                let tasks = query.order(taskname.asc()).load::<models::Task>(conn)?;
+                for taskobj in tasks.iter() {
+                    helper::delete_worklogs(conn, taskobj.id)?;
+                    helper::delete_task_tags(conn, taskobj.id)?;
+                    diesel::delete(taskobj).execute(conn)?;
+                }
+                helper::prune_tags(conn)?;
+                Ok(tasks.into_iter().map(|t| t.taskname).collect())
+            })
+        })
+    }
+
+    pub fn check_task_exists(&self, name: &str) -> Result<bool, Error> {
+        use diesel::dsl::{exists, select};
+        use schema::task::dsl::*;
+        let conn = &self.conn;
+        match select(exists(task.filter(taskname.eq(name)))).get_result(conn) {
+            Ok(val) => Ok(val),
+            Err(err) => Err(err.into()),
         }
     }
 
-    match diesel::update(&updatetask).set(&updatetask).execute(&conn) {
-        Ok(_val) => {
-            println!("{} updated.", Style::new().bold().paint(name));
+    /// Attach `tags` to task `name`, creating tags that do not exist yet.
+    pub fn add_tags(&self, name: &str, tags: &[String]) -> Result<(), Error> {
+        self.record(&format!("tag {}", name), || {
+            let conn = &self.conn;
+            let taskid = helper::get_task_id(conn, name)?;
+            for tagname in tags.iter() {
+                let link = models::TaskTag {
+                    task_id: taskid,
+                    tag_id: helper::get_or_create_tag_id(conn, tagname)?,
+                };
+                diesel::replace_into(schema::task_tag::table)
+                    .values(&link)
+                    .execute(conn)?;
+            }
             Ok(())
+        })
+    }
+
+    /// Detach `tags` from task `name`. Tags the task does not have are ignored,
+    /// tags left without any task are deleted.
+    pub fn remove_tags(&self, name: &str, tags: &[String]) -> Result<(), Error> {
+        self.record(&format!("untag {}", name), || {
+            use schema::{tag, task_tag};
+            let conn = &self.conn;
+            let taskid = helper::get_task_id(conn, name)?;
+            let tagids = tag::table.filter(tag::name.eq_any(tags)).select(tag::id);
+            diesel::delete(
+                task_tag::table
+                    .filter(task_tag::task_id.eq(taskid))
+                    .filter(task_tag::tag_id.eq_any(tagids)),
+            )
+            .execute(conn)?;
+            helper::prune_tags(conn)
+        })
+    }
+
+    /// Tag names of all tagged tasks by task id, sorted by name.
+    pub fn get_task_tags(&self) -> Result<HashMap<i32, Vec<String>>, Error> {
+        use schema::{tag, task_tag};
+        let rows = task_tag::table
+            .inner_join(tag::table)
+            .select((task_tag::task_id, tag::name))
+            .order(tag::name)
+            .load::<(i32, String)>(&self.conn)?;
+        let mut tags: HashMap<i32, Vec<String>> = HashMap::new();
+        for (taskid, name) in rows.into_iter() {
+            tags.entry(taskid).or_default().push(name);
         }
-        Err(err) => Err(err.into()),
+        Ok(tags)
+    }
+
+    /// List tags with the number of their tasks and time spent on those tasks.
+    ///
+    /// Tasks in the trash are not counted, running worklogs count up to now.
+    pub fn list_tags(&self) -> Result<Vec<models::TagSpent>, Error> {
+        use diesel::sql_types::BigInt;
+        let rows = diesel::sql_query(format!(
+            "SELECT tag.*, \
+                CAST(COUNT(DISTINCT task_tag.task_id) AS INTEGER) AS tasks, \
+                CAST(COALESCE(SUM(CASE WHEN worklog.stopped IS NULL \
+                    THEN {running} \
+                    ELSE worklog.duration END), 0) AS INTEGER) AS spent \
+            FROM tag INNER JOIN task_tag ON task_tag.tag_id = tag.id \
+                INNER JOIN task ON task.id = task_tag.task_id AND task.deleted_at IS NULL \
+                LEFT JOIN worklog \
+                ON worklog.task_id = task_tag.task_id AND worklog.ignored = 0 \
+            GROUP BY tag.id \
+            ORDER BY tag.name",
+            running = helper::RUNNING_SECONDS
+        ))
+        .bind::<BigInt, _>(get_timestamp().timestamp())
+        .load::<models::TagSpent>(&self.conn)?;
+        Ok(rows)
+    }
+
+    /// Start multiple tasks.
+    ///
+    /// `at` is an optional time to use instead of current time and `message` an
+    /// optional note for the new sessions. Completed and already running tasks, and
+    /// with `single_active` any task while another one is running, are skipped and
+    /// reported by an error in their place of the result, other errors abort
+    /// without starting any task.
+    pub fn start_worklogs(
+        &self,
+        names: &[String],
+        at: Option<DateTime<Utc>>,
+        message: Option<&str>,
+    ) -> Result<Vec<Result<WorklogEvent, Error>>, Error> {
+        self.record(&format!("start {}", names.join(" ")), || {
+            let conn = &self.conn;
+            let timestamp = at.unwrap_or_else(get_timestamp);
+            helper::in_transaction(conn, false, || {
+                let mut events = Vec::with_capacity(names.len());
+                for name in names.iter() {
+                    let current_task = helper::get_task(conn, name)?;
+                    if current_task.done {
+                        events.push(Err(Error::TaskIsCompleted {
+                            taskname: current_task.taskname,
+                        }));
+                        continue;
+                    }
+                    if helper::check_task_is_running(conn, &current_task)? {
+                        helper::ignore_invalid_worklogs(conn, &current_task)?;
+                        events.push(Err(Error::TaskIsAlreadyRunning {
+                            taskname: current_task.taskname,
+                        }));
+                        continue;
+                    }
+                    if self.single_active {
+                        if let Some(taskid) = helper::get_running_task_ids(conn, &[])?.first() {
+                            events.push(Err(Error::AnotherTaskIsRunning {
+                                taskname: helper::get_task_by_id(conn, *taskid)?.taskname,
+                            }));
+                            continue;
+                        }
+                    }
+                    if at.is_some()
+                        && helper::check_worklog_overlap(
+                            conn,
+                            &current_task,
+                            timestamp.into(),
+                            None,
+                            None,
+                        )?
+                    {
+                        return Err(Error::WorklogOverlaps {
+                            taskname: current_task.taskname,
+                        });
+                    }
+                    helper::create_worklog(conn, current_task.id, at.map(Timestamp), message)?;
+                    events.push(Ok(WorklogEvent {
+                        taskname: current_task.taskname,
+                        timestamp,
+                        completed: false,
+                    }));
+                }
+                Ok(events)
+            })
+        })
     }
-}
 
-pub fn delete_task(config: &Config, name: &str) -> Result<(), BoxError> {
-    use schema::task::dsl::*;
-    let conn = get_connection(config)?;
+    /// Stop multiple tasks
+    ///
+    /// `at` is an optional time to use instead of current time and `message` is
+    /// appended to the notes of the stopped sessions. Tasks that are not running
+    /// are reported by an error in their place of the result, other errors abort
+    /// without stopping any task.
+    pub fn stop_worklogs(
+        &self,
+        names: &[String],
+        at: Option<DateTime<Utc>>,
+        message: Option<&str>,
+    ) -> Result<Vec<Result<WorklogEvent, Error>>, Error> {
+        let stops: Vec<(String, Option<DateTime<Utc>>)> =
+            names.iter().map(|name| (name.to_owned(), at)).collect();
+        self.stop_worklogs_at(&stops, message)
+    }
 
-    // remove worklogs
-    let taskid = helper::get_task_id(&conn, name)?;
-    helper::delete_worklogs(&conn, taskid)?;
+    /// Stop multiple tasks, each at its own time
+    ///
+    /// `stops` pairs task names with an optional time to use instead of current
+    /// time, otherwise like `stop_worklogs`.
+    pub fn stop_worklogs_at(
+        &self,
+        stops: &[(String, Option<DateTime<Utc>>)],
+        message: Option<&str>,
+    ) -> Result<Vec<Result<WorklogEvent, Error>>, Error> {
+        let names: Vec<&str> = stops.iter().map(|(name, _)| name.as_str()).collect();
+        self.record(&format!("stop {}", names.join(" ")), || {
+            let conn = &self.conn;
+            let now = get_timestamp();
+            helper::in_transaction(conn, false, || {
+                let mut events = Vec::with_capacity(stops.len());
+                for (name, at) in stops.iter() {
+                    let timestamp = at.unwrap_or(now);
+                    let current_task = helper::get_task(conn, name)?;
+                    if !helper::check_task_is_running(conn, &current_task)? {
+                        // no running worklog
+                        events.push(Err(Error::TaskIsNotRunning {
+                            taskname: current_task.taskname,
+                        }));
+                        continue;
+                    }
+                    events.push(Ok(self.stop_running(current_task, timestamp, message)?));
+                }
+                Ok(events)
+            })
+        })
+    }
 
-    diesel::delete(task.filter(taskname.eq(name))).execute(&conn)?;
-    println!("{} deleted.", Style::new().bold().paint(name));
-    Ok(())
-}
+    /// Stop every running task and start task `name`, all at the same time.
+    ///
+    /// `at` is an optional time to use instead of current time and `message` an
+    /// optional note for the new session. A running task `name` keeps its session.
+    /// Nothing is changed when any step fails.
+    pub fn switch_worklog(
+        &self,
+        name: &str,
+        at: Option<DateTime<Utc>>,
+        message: Option<&str>,
+    ) -> Result<SwitchEvent, Error> {
+        self.record(&format!("switch {}", name), || {
+            let conn = &self.conn;
+            let timestamp = at.unwrap_or_else(get_timestamp);
+            helper::in_transaction(conn, false, || {
+                let current_task = helper::get_task(conn, name)?;
+                if current_task.done {
+                    return Err(Error::TaskIsCompleted {
+                        taskname: current_task.taskname,
+                    });
+                }
+                let taskids = helper::get_running_task_ids(conn, &[])?;
+                let mut stopped = Vec::with_capacity(taskids.len());
+                for taskobj in helper::get_tasks(conn, &taskids)? {
+                    if taskobj.id != current_task.id {
+                        stopped.push(self.stop_running(taskobj, timestamp, None)?);
+                    }
+                }
+                if taskids.contains(&current_task.id) {
+                    helper::ignore_invalid_worklogs(conn, &current_task)?;
+                    return Ok(SwitchEvent {
+                        stopped,
+                        started: None,
+                    });
+                }
+                if at.is_some()
+                    && helper::check_worklog_overlap(
+                        conn,
+                        &current_task,
+                        timestamp.into(),
+                        None,
+                        None,
+                    )?
+                {
+                    return Err(Error::WorklogOverlaps {
+                        taskname: current_task.taskname,
+                    });
+                }
+                helper::create_worklog(conn, current_task.id, Some(timestamp.into()), message)?;
+                Ok(SwitchEvent {
+                    stopped,
+                    started: Some(WorklogEvent {
+                        taskname: current_task.taskname,
+                        timestamp,
+                        completed: false,
+                    }),
+                })
+            })
+        })
+    }
 
-pub fn check_task_exists(config: &Config, name: &str) -> Result<bool, BoxError> {
-    use diesel::dsl::{exists, select};
-    use schema::task::dsl::*;
-    let conn = get_connection(config)?;
-    match select(exists(task.filter(taskname.eq(name)))).get_result(&conn) {
-        Ok(val) => Ok(val),
-        Err(err) => Err(err.into()),
+    /// Stop running task `current_task`, flagging it done with `autodone`.
+    fn stop_running(
+        &self,
+        current_task: models::Task,
+        timestamp: DateTime<Utc>,
+        message: Option<&str>,
+    ) -> Result<WorklogEvent, Error> {
+        let conn = &self.conn;
+        helper::ignore_invalid_worklogs(conn, &current_task)?;
+        helper::stop_worklog(conn, &current_task, Some(timestamp.into()), message)?;
+        let completed = self.autodone
+            && current_task.allocated > 0
+            && helper::get_spent_time(conn, &current_task)? >= current_task.allocated;
+        if completed {
+            helper::flag_complete(conn, &current_task)?;
+        }
+        Ok(WorklogEvent {
+            taskname: current_task.taskname,
+            timestamp,
+            completed,
+        })
     }
-}
 
-pub fn start_worklogs(config: &Config, names: &[String]) -> Result<(), BoxError> {
-    let conn = get_connection(config)?;
-    for name in names.iter() {
-        let current_task = helper::get_task(&conn, name)?;
-        if current_task.done {
-            eprintln!(
-                "Cannot start completed task: {}",
-                Style::new().bold().paint(current_task.taskname)
+    /// Pause multiple running tasks, starting a break in their current session.
+    ///
+    /// `at` is an optional time to use instead of current time. Tasks that are not
+    /// running or already paused are reported by an error in their place of the
+    /// result, other errors abort without pausing any task.
+    pub fn pause_worklogs(
+        &self,
+        names: &[String],
+        at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Result<WorklogEvent, Error>>, Error> {
+        self.record(&format!("pause {}", names.join(" ")), || {
+            let conn = &self.conn;
+            let timestamp = at.unwrap_or_else(get_timestamp);
+            helper::in_transaction(conn, false, || {
+                let mut events = Vec::with_capacity(names.len());
+                for name in names.iter() {
+                    let current_task = helper::get_task(conn, name)?;
+                    if !helper::check_task_is_running(conn, &current_task)? {
+                        events.push(Err(Error::TaskIsNotRunning {
+                            taskname: current_task.taskname,
+                        }));
+                        continue;
+                    }
+                    helper::ignore_invalid_worklogs(conn, &current_task)?;
+                    let worklog_obj = helper::get_running_worklog(conn, &current_task)?;
+                    if helper::get_open_pause(conn, &worklog_obj)?.is_some() {
+                        events.push(Err(Error::TaskIsPaused {
+                            taskname: current_task.taskname,
+                        }));
+                        continue;
+                    }
+                    if timestamp < *worklog_obj.started {
+                        return Err(Error::InvalidTimeRange);
+                    }
+                    let new_pause = models::NewPause {
+                        worklog_id: worklog_obj.id,
+                        started: Timestamp(timestamp),
+                    };
+                    diesel::insert_into(schema::pause::table)
+                        .values(&new_pause)
+                        .execute(conn)?;
+                    events.push(Ok(WorklogEvent {
+                        taskname: current_task.taskname,
+                        timestamp,
+                        completed: false,
+                    }));
+                }
+                Ok(events)
+            })
+        })
+    }
+
+    /// Resume multiple paused tasks, ending the break in their current session.
+    ///
+    /// `at` is an optional time to use instead of current time. Tasks that are not
+    /// paused are reported by an error in their place of the result, other errors
+    /// abort without resuming any task.
+    pub fn resume_worklogs(
+        &self,
+        names: &[String],
+        at: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Result<WorklogEvent, Error>>, Error> {
+        self.record(&format!("resume {}", names.join(" ")), || {
+            use schema::pause::dsl::*;
+            let conn = &self.conn;
+            let timestamp = at.unwrap_or_else(get_timestamp);
+            helper::in_transaction(conn, false, || {
+                let mut events = Vec::with_capacity(names.len());
+                for name in names.iter() {
+                    let current_task = helper::get_task(conn, name)?;
+                    if !helper::check_task_is_running(conn, &current_task)? {
+                        events.push(Err(Error::TaskIsNotRunning {
+                            taskname: current_task.taskname,
+                        }));
+                        continue;
+                    }
+                    helper::ignore_invalid_worklogs(conn, &current_task)?;
+                    let worklog_obj = helper::get_running_worklog(conn, &current_task)?;
+                    let pause_obj = match helper::get_open_pause(conn, &worklog_obj)? {
+                        Some(val) => val,
+                        None => {
+                            events.push(Err(Error::TaskIsNotPaused {
+                                taskname: current_task.taskname,
+                            }));
+                            continue;
+                        }
+                    };
+                    if timestamp < *pause_obj.started {
+                        return Err(Error::InvalidTimeRange);
+                    }
+                    diesel::update(&pause_obj)
+                        .set(stopped.eq(Timestamp(timestamp)))
+                        .execute(conn)?;
+                    events.push(Ok(WorklogEvent {
+                        taskname: current_task.taskname,
+                        timestamp,
+                        completed: false,
+                    }));
+                }
+                Ok(events)
+            })
+        })
+    }
+
+    /// Record a completed worklog for task `name`.
+    ///
+    /// Fails if the period overlaps any non-ignored worklog of the same task.
+    pub fn log_worklog(
+        &self,
+        name: &str,
+        started: DateTime<Utc>,
+        stopped: DateTime<Utc>,
+    ) -> Result<models::Worklog, Error> {
+        self.record(&format!("log {}", name), || {
+            let conn = &self.conn;
+            let current_task = helper::get_task(conn, name)?;
+            let seconds = seconds_between(&started, &stopped)?;
+            if seconds <= 0 {
+                return Err(Error::InvalidTimeRange);
+            }
+            let (started, stopped) = (Timestamp(started), Timestamp(stopped));
+            if helper::check_worklog_overlap(conn, &current_task, started, Some(stopped), None)? {
+                return Err(Error::WorklogOverlaps {
+                    taskname: current_task.taskname,
+                });
+            }
+            helper::create_stopped_worklog(conn, current_task.id, started, stopped, seconds)
+        })
+    }
+
+    /// List worklogs along with their task, latest `limit` entries ordered by `started`.
+    ///
+    /// `taskfilter` works the same as in `list_tasks`.
+    pub fn list_worklogs(
+        &self,
+        taskfilter: Option<&str>,
+        include_ignored: bool,
+        limit: Option<i64>,
+    ) -> Result<Vec<(models::Worklog, models::Task)>, Error> {
+        use schema::{task, worklog};
+        let conn = &self.conn;
+        let mut query = worklog::table
+            .inner_join(task::table)
+            .filter(task::deleted_at.is_null())
+            .into_boxed();
+        if let Some(taskfilter) = taskfilter {
+            let mut parent = String::from(taskfilter);
+            parent.push_str("::%");
+            query = query.filter(
+                task::taskname
+                    .like(taskfilter)
+                    .or(task::taskname.like(parent)),
             );
-            continue;
         }
-        if helper::check_task_is_running(&conn, &current_task)? {
-            helper::ignore_invalid_worklogs(&conn, &current_task)?;
-            eprintln!(
-                "{} is already running.",
-                Style::new().bold().paint(current_task.taskname),
-            );
-            continue;
+        if !include_ignored {
+            query = query.filter(worklog::ignored.eq(false));
         }
-        helper::create_worklog(&conn, current_task.id)?;
-        println!(
-            "{} started at {}.",
-            Style::new().bold().paint(current_task.taskname),
-            get_timestamp()
-        );
+        query = query.order((worklog::started.desc(), worklog::id.desc()));
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        let mut data = query.load::<(models::Worklog, models::Task)>(conn)?;
+        data.reverse();
+        Ok(data)
     }
-    Ok(())
-}
 
-/// Stop multiple tasks
-pub fn stop_worklogs(config: &Config, names: &[String]) -> Result<(), BoxError> {
-    if names.is_empty() {
-        println!("No running task");
-        return Ok(());
-    }
-    let conn = get_connection(config)?;
-    for name in names.iter() {
-        let current_task = helper::get_task(&conn, &name)?;
-        if !helper::check_task_is_running(&conn, &current_task)? {
-            // no running worklog
-            eprintln!(
-                "{} is not running",
-                Style::new().bold().paint(&current_task.taskname),
+    /// Change `started` and/or `stopped` of a worklog, recomputing its `duration`.
+    ///
+    /// Setting `stopped` of a running worklog stops it, ending an open break at the
+    /// same time. Breaks outside the new period are not counted.
+    pub fn update_worklog(
+        &self,
+        worklog_id: i32,
+        started: Option<DateTime<Utc>>,
+        stopped: Option<DateTime<Utc>>,
+    ) -> Result<models::Worklog, Error> {
+        self.record(&format!("worklog edit {}", worklog_id), || {
+            use schema::worklog::dsl;
+            let conn = &self.conn;
+            helper::in_transaction(conn, false, || {
+                let worklog_obj = helper::get_worklog(conn, worklog_id)?;
+                let taskobj = helper::get_task_by_id(conn, worklog_obj.task_id)?;
+
+                let new_started = started.map_or(worklog_obj.started, Timestamp);
+                let new_stopped = stopped.map(Timestamp).or(worklog_obj.stopped);
+                let seconds = match new_stopped {
+                    Some(stop) => seconds_between(&new_started, &stop)?,
+                    None => 0,
+                };
+                if seconds < 0 {
+                    return Err(Error::InvalidTimeRange);
+                }
+                // ending a paused session ends its break too
+                let open_pause = match new_stopped {
+                    Some(stop) => match helper::get_open_pause(conn, &worklog_obj)? {
+                        Some(pause_obj) if stop < pause_obj.started => {
+                            return Err(Error::InvalidTimeRange)
+                        }
+                        open_pause => open_pause,
+                    },
+                    None => None,
+                };
+                if !worklog_obj.ignored
+                    && helper::check_worklog_overlap(
+                        conn,
+                        &taskobj,
+                        new_started,
+                        new_stopped,
+                        Some(worklog_obj.id),
+                    )?
+                {
+                    return Err(Error::WorklogOverlaps {
+                        taskname: taskobj.taskname,
+                    });
+                }
+
+                if let Some(pause_obj) = open_pause {
+                    diesel::update(&pause_obj)
+                        .set(schema::pause::stopped.eq(new_stopped))
+                        .execute(conn)?;
+                }
+                let seconds = match new_stopped {
+                    Some(stop) => {
+                        seconds - helper::get_break_time(conn, worklog_obj.id, new_started, stop)?
+                    }
+                    None => 0,
+                };
+                diesel::update(&worklog_obj)
+                    .set((
+                        dsl::started.eq(new_started),
+                        dsl::stopped.eq(new_stopped),
+                        dsl::duration.eq(seconds),
+                    ))
+                    .execute(conn)?;
+                helper::get_worklog(conn, worklog_obj.id)
+            })
+        })
+    }
+
+    pub fn delete_worklog(&self, worklog_id: i32) -> Result<(), Error> {
+        self.record(&format!("worklog delete {}", worklog_id), || {
+            let conn = &self.conn;
+            helper::in_transaction(conn, false, || {
+                let worklog_obj = helper::get_worklog(conn, worklog_id)?;
+                diesel::delete(models::Pause::belonging_to(&worklog_obj)).execute(conn)?;
+                diesel::delete(&worklog_obj).execute(conn)?;
+                Ok(())
+            })
+        })
+    }
+
+    /// Set `ignored` flag of a worklog.
+    ///
+    /// Ignored worklogs are not counted as spent time. A worklog cannot be unignored
+    /// when it would overlap another worklog, e.g. a second running session.
+    pub fn set_worklog_ignored(&self, worklog_id: i32, value: bool) -> Result<(), Error> {
+        self.record(
+            &format!(
+                "worklog {} {}",
+                if value { "ignore" } else { "unignore" },
+                worklog_id
+            ),
+            || {
+                use schema::worklog::dsl::*;
+                let conn = &self.conn;
+                let worklog_obj = helper::get_worklog(conn, worklog_id)?;
+                if !value && worklog_obj.ignored {
+                    let taskobj = helper::get_task_by_id(conn, worklog_obj.task_id)?;
+                    if worklog_obj.stopped.is_none()
+                        && helper::check_task_is_running(conn, &taskobj)?
+                    {
+                        return Err(Error::TaskIsAlreadyRunning {
+                            taskname: taskobj.taskname,
+                        });
+                    }
+                    if helper::check_worklog_overlap(
+                        conn,
+                        &taskobj,
+                        worklog_obj.started,
+                        worklog_obj.stopped,
+                        Some(worklog_obj.id),
+                    )? {
+                        return Err(Error::WorklogOverlaps {
+                            taskname: taskobj.taskname,
+                        });
+                    }
+                }
+                diesel::update(&worklog_obj)
+                    .set(ignored.eq(value))
+                    .execute(conn)?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Sum non-ignored worklog durations per local day and task.
+    ///
+    /// Covers local dates from `from` to `to`, both inclusive. Sessions crossing
+    /// midnight are split between the days, running sessions count up to now.
+    /// Breaks are summed separately and not counted as spent.
+    pub fn get_report(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+        taskfilter: Option<&str>,
+        tags: &[String],
+    ) -> Result<Vec<ReportEntry>, Error> {
+        use schema::{task, worklog};
+        let conn = &self.conn;
+        let range_start = local_date_to_utc(from)?.with_timezone(&Local);
+        let range_end = local_date_to_utc(to.succ())?.with_timezone(&Local);
+
+        let mut query = worklog::table
+            .inner_join(task::table)
+            .filter(task::deleted_at.is_null())
+            .filter(worklog::ignored.eq(false))
+            .filter(worklog::started.lt(range_end.timestamp()))
+            .filter(
+                worklog::stopped
+                    .is_null()
+                    .or(worklog::stopped.gt(range_start.timestamp())),
+            )
+            .into_boxed();
+        if let Some(taskfilter) = taskfilter {
+            let mut parent = String::from(taskfilter);
+            parent.push_str("::%");
+            query = query.filter(
+                task::taskname
+                    .like(taskfilter)
+                    .or(task::taskname.like(parent)),
             );
-            continue;
-        }
-        helper::ignore_invalid_worklogs(&conn, &current_task)?;
-        helper::stop_worklog(&conn, &current_task)?;
-        print!(
-            "{} stopped at {}",
-            Style::new().bold().paint(&current_task.taskname),
-            get_timestamp()
-        );
-        if config.autodone
-            && current_task.allocated > 0
-            && helper::get_spent_time(&conn, &current_task)? >= current_task.allocated
+        }
+        if let Some(ids) = helper::get_tagged_task_ids(conn, tags)? {
+            query = query.filter(task::id.eq_any(ids));
+        }
+        let data = query
+            .order(worklog::started)
+            .load::<(models::Worklog, models::Task)>(conn)?;
+        let worklog_ids: Vec<i32> = data.iter().map(|(w, _)| w.id).collect();
+        let mut pauses: HashMap<i32, Vec<models::Pause>> = HashMap::new();
+        for pause_obj in schema::pause::table
+            .filter(schema::pause::worklog_id.eq_any(worklog_ids))
+            .load::<models::Pause>(conn)?
         {
-            helper::flag_complete(&conn, &current_task)?;
-            print!(" [{}]", Style::new().bold().paint("done"));
+            pauses
+                .entry(pause_obj.worklog_id)
+                .or_default()
+                .push(pause_obj);
         }
-        println!(".");
+
+        fn report_entry<'a>(
+            entries: &'a mut BTreeMap<(NaiveDate, String), ReportEntry>,
+            date: NaiveDate,
+            taskname: &str,
+        ) -> &'a mut ReportEntry {
+            entries
+                .entry((date, taskname.to_owned()))
+                .or_insert_with(|| ReportEntry {
+                    date,
+                    taskname: taskname.to_owned(),
+                    spent: 0,
+                    breaks: 0,
+                    notes: Vec::new(),
+                })
+        }
+
+        let now = get_timestamp();
+        let mut entries: BTreeMap<(NaiveDate, String), ReportEntry> = BTreeMap::new();
+        for (worklog_obj, task_obj) in data.iter() {
+            let started = worklog_obj.started.with_timezone(&Local);
+            let stopped = worklog_obj
+                .stopped
+                .map_or(now, |s| s.0)
+                .with_timezone(&Local);
+            let started = std::cmp::max(started, range_start);
+            let stopped = std::cmp::min(stopped, range_end);
+            for (date, seconds) in split_by_day(&started, &stopped) {
+                let entry = report_entry(&mut entries, date, &task_obj.taskname);
+                entry.spent += seconds;
+                entry.notes.extend(worklog_obj.notes.iter().cloned());
+            }
+            for pause_obj in pauses.get(&worklog_obj.id).into_iter().flatten() {
+                let pause_started = pause_obj.started.with_timezone(&Local);
+                let pause_stopped = pause_obj.stopped.map_or(now, |s| s.0).with_timezone(&Local);
+                let pause_started = std::cmp::max(pause_started, started);
+                let pause_stopped = std::cmp::min(pause_stopped, stopped);
+                for (date, seconds) in split_by_day(&pause_started, &pause_stopped) {
+                    let entry = report_entry(&mut entries, date, &task_obj.taskname);
+                    entry.spent -= seconds;
+                    entry.breaks += seconds;
+                }
+            }
+        }
+
+        Ok(entries.into_values().collect())
     }
-    Ok(())
-}
 
-pub fn get_total_spent(config: &Config, name: &str) -> Result<i32, BoxError> {
-    let conn = get_connection(config)?;
-    let taskobj = helper::get_task(&conn, name)?;
-    helper::get_spent_time(&conn, &taskobj)
-}
+    /// Dump every task and worklog row into an archive.
+    pub fn export_archive(&self) -> Result<Archive, Error> {
+        let conn = &self.conn;
+        let tasks = schema::task::table
+            .order(schema::task::id)
+            .load::<models::Task>(conn)?;
+        let worklogs = schema::worklog::table
+            .order(schema::worklog::id)
+            .load::<models::Worklog>(conn)?;
+        let pauses = schema::pause::table
+            .order(schema::pause::id)
+            .load::<models::Pause>(conn)?;
+        let tags = schema::tag::table
+            .order(schema::tag::id)
+            .load::<models::Tag>(conn)?;
+        let task_tags = schema::task_tag::table
+            .order((schema::task_tag::task_id, schema::task_tag::tag_id))
+            .load::<models::TaskTag>(conn)?;
+        Ok(Archive {
+            version: ARCHIVE_VERSION,
+            exported: utc_to_iso8601(&get_timestamp()),
+            tasks,
+            worklogs,
+            pauses,
+            tags,
+            task_tags,
+        })
+    }
 
-#[derive(Debug)]
-pub struct RunningTask {
-    pub name: String,
-    pub spent: i32,         // total spent
-    pub current_spent: i32, // current session
-    pub started: String,    // last started in UTC
-}
+    /// Load tasks, worklogs, pauses and tags of `archive` in a single transaction.
+    ///
+    /// Fails on any existing task name unless `merge` is set, in which case tasks are
+    /// matched by `taskname` and worklogs of the same task and `started` are skipped
+    /// along with their pauses. Tasks in the trash are only merged with tasks that
+    /// are in the trash in the archive too, so imported time is not hidden.
+    /// With `dry_run` the transaction is rolled back after counting.
+    pub fn import_archive(
+        &self,
+        archive: &Archive,
+        merge: bool,
+        dry_run: bool,
+    ) -> Result<ImportSummary, Error> {
+        self.record("import", || {
+            let conn = &self.conn;
+            helper::in_transaction(conn, dry_run, || {
+                let mut summary = ImportSummary::default();
+                // archive task id -> database task id
+                let mut task_ids: HashMap<i32, i32> = HashMap::new();
+                // archive worklog id -> created worklog id, `None` when skipped
+                let mut worklog_ids: HashMap<i32, Option<i32>> = HashMap::new();
+                for taskobj in archive.tasks.iter() {
+                    if let Some(existing) = helper::find_task(conn, &taskobj.taskname)? {
+                        if !merge {
+                            return Err(Error::TaskAlreadyExists {
+                                taskname: existing.taskname,
+                            });
+                        }
+                        if existing.deleted_at.is_some() && taskobj.deleted_at.is_none() {
+                            return Err(Error::TaskIsTrashed {
+                                taskname: existing.taskname,
+                            });
+                        }
+                        task_ids.insert(taskobj.id, existing.id);
+                        summary.tasks_merged += 1;
+                        continue;
+                    }
+                    let new_task = models::NewTask {
+                        taskname: &taskobj.taskname,
+                        notes: taskobj.notes.as_deref(),
+                        allocated: Some(taskobj.allocated),
+                        duedate: taskobj.duedate.as_deref(),
+                        created: Some(taskobj.created),
+                        done: Some(taskobj.done),
+                        deleted_at: taskobj.deleted_at,
+                        recur: taskobj.recur.as_deref(),
+                    };
+                    diesel::insert_into(schema::task::table)
+                        .values(&new_task)
+                        .execute(conn)?;
+                    task_ids.insert(taskobj.id, helper::get_task_id(conn, &taskobj.taskname)?);
+                    summary.tasks_created += 1;
+                    summary.new_tasks.push(taskobj.taskname.to_owned());
+                }
 
-pub fn get_running_tasks(
-    config: &Config,
-    taskfilter: Option<&str>,
-) -> Result<Vec<RunningTask>, BoxError> {
-    let conn = get_connection(config)?;
+                for worklog_obj in archive.worklogs.iter() {
+                    let taskid = match task_ids.get(&worklog_obj.task_id) {
+                        Some(val) => *val,
+                        None => {
+                            return Err(Error::InvalidData(format!(
+                                "worklog #{} refers to missing task #{}",
+                                worklog_obj.id, worklog_obj.task_id
+                            )))
+                        }
+                    };
+                    let duplicate =
+                        helper::check_worklog_exists(conn, taskid, worklog_obj.started)?;
+                    let second_running = worklog_obj.stopped.is_none()
+                        && !worklog_obj.ignored
+                        && helper::check_task_is_running(
+                            conn,
+                            &helper::get_task_by_id(conn, taskid)?,
+                        )?;
+                    if duplicate || second_running {
+                        worklog_ids.insert(worklog_obj.id, None);
+                        summary.worklogs_skipped += 1;
+                        continue;
+                    }
+                    let new_worklog = models::NewWorklog {
+                        task_id: taskid,
+                        started: Some(worklog_obj.started),
+                        stopped: worklog_obj.stopped,
+                        duration: Some(worklog_obj.duration),
+                        ignored: Some(worklog_obj.ignored),
+                        notes: worklog_obj.notes.as_deref(),
+                    };
+                    diesel::insert_into(schema::worklog::table)
+                        .values(&new_worklog)
+                        .execute(conn)?;
+                    let worklogid = {
+                        use schema::worklog::dsl::*;
+                        worklog
+                            .filter(task_id.eq(taskid))
+                            .filter(started.eq(worklog_obj.started))
+                            .select(id)
+                            .order(id.desc())
+                            .first::<i32>(conn)?
+                    };
+                    worklog_ids.insert(worklog_obj.id, Some(worklogid));
+                    summary.worklogs_created += 1;
+                }
 
-    let mut ids: Vec<i32> = Vec::new();
+                for pause_obj in archive.pauses.iter() {
+                    use schema::pause::dsl::*;
+                    let worklogid = match worklog_ids.get(&pause_obj.worklog_id) {
+                        Some(Some(val)) => *val,
+                        Some(None) => continue,
+                        None => {
+                            return Err(Error::InvalidData(format!(
+                                "pause #{} refers to missing worklog #{}",
+                                pause_obj.id, pause_obj.worklog_id
+                            )))
+                        }
+                    };
+                    diesel::insert_into(pause)
+                        .values((
+                            worklog_id.eq(worklogid),
+                            started.eq(pause_obj.started),
+                            stopped.eq(pause_obj.stopped),
+                        ))
+                        .execute(conn)?;
+                }
 
-    if let Some(_filter) = taskfilter {
-        let tasks = list_tasks(config, taskfilter, None)?;
-        ids = tasks.into_iter().map(|t| t.id).collect();
+                // archive tag id -> database tag id, tags are matched by name
+                let mut tag_ids: HashMap<i32, i32> = HashMap::new();
+                for tagobj in archive.tags.iter() {
+                    tag_ids.insert(tagobj.id, helper::get_or_create_tag_id(conn, &tagobj.name)?);
+                }
+                for link in archive.task_tags.iter() {
+                    let (taskid, tagid) =
+                        match (task_ids.get(&link.task_id), tag_ids.get(&link.tag_id)) {
+                            (Some(taskid), Some(tagid)) => (*taskid, *tagid),
+                            _ => {
+                                return Err(Error::InvalidData(format!(
+                                    "tag #{} of task #{} is missing",
+                                    link.tag_id, link.task_id
+                                )))
+                            }
+                        };
+                    let link = models::TaskTag {
+                        task_id: taskid,
+                        tag_id: tagid,
+                    };
+                    diesel::replace_into(schema::task_tag::table)
+                        .values(&link)
+                        .execute(conn)?;
+                }
+                Ok(summary)
+            })
+        })
+    }
+
+    /// Insert completed worklogs read from another time tracker.
+    ///
+    /// Missing tasks are created, entries overlapping an existing worklog of the same
+    /// task or ending before they start are skipped. Fails on entries of a task in
+    /// the trash. With `dry_run` the transaction is rolled back after counting.
+    pub fn import_entries(
+        &self,
+        entries: &[ImportedEntry],
+        dry_run: bool,
+    ) -> Result<ImportSummary, Error> {
+        self.record("import", || {
+            let conn = &self.conn;
+            helper::in_transaction(conn, dry_run, || {
+                let mut summary = ImportSummary::default();
+                let mut tasks: HashMap<&str, models::Task> = HashMap::new();
+                for entry in entries.iter() {
+                    if !tasks.contains_key(entry.taskname.as_str()) {
+                        let taskobj = match helper::find_task(conn, &entry.taskname)? {
+                            Some(taskobj) if taskobj.deleted_at.is_some() => {
+                                return Err(Error::TaskIsTrashed {
+                                    taskname: taskobj.taskname,
+                                })
+                            }
+                            Some(taskobj) => {
+                                summary.tasks_merged += 1;
+                                taskobj
+                            }
+                            None => {
+                                let new_task = models::NewTask {
+                                    taskname: &entry.taskname,
+                                    ..Default::default()
+                                };
+                                diesel::insert_into(schema::task::table)
+                                    .values(&new_task)
+                                    .execute(conn)?;
+                                summary.tasks_created += 1;
+                                summary.new_tasks.push(entry.taskname.to_owned());
+                                helper::get_task(conn, &entry.taskname)?
+                            }
+                        };
+                        tasks.insert(&entry.taskname, taskobj);
+                    }
+                    let taskobj = &tasks[entry.taskname.as_str()];
+
+                    let seconds = seconds_between(&entry.started, &entry.stopped)?;
+                    let (started, stopped) = (Timestamp(entry.started), Timestamp(entry.stopped));
+                    if seconds < 0
+                        || helper::check_worklog_overlap(
+                            conn,
+                            taskobj,
+                            started,
+                            Some(stopped),
+                            None,
+                        )?
+                    {
+                        summary.worklogs_skipped += 1;
+                        continue;
+                    }
+                    helper::create_stopped_worklog(conn, taskobj.id, started, stopped, seconds)?;
+                    summary.worklogs_created += 1;
+                }
+                Ok(summary)
+            })
+        })
     }
-    let taskids = helper::get_running_task_ids(&conn, &ids)?;
-    let tasks = helper::get_tasks(&conn, &taskids)?;
 
-    let mut result: Vec<RunningTask> = Vec::new();
-    for task in tasks.iter() {
-        result.push(RunningTask {
-            name: task.taskname.to_owned(),
-            spent: helper::get_spent_time(&conn, task)?,
-            current_spent: helper::get_current_spent_time(&conn, task)?,
-            started: helper::get_started_time(&conn, task)?,
-        });
+    pub fn get_total_spent(&self, name: &str) -> Result<i32, Error> {
+        let conn = &self.conn;
+        let taskobj = helper::get_task(conn, name)?;
+        helper::get_spent_time(conn, &taskobj)
+    }
+
+    /// List running tasks, filtered the same way as `list_tasks`.
+    pub fn get_running_tasks(
+        &self,
+        taskfilter: Option<&str>,
+        tags: &[String],
+    ) -> Result<Vec<RunningTask>, Error> {
+        let conn = &self.conn;
+
+        let mut ids: Vec<i32> = Vec::new();
+
+        if taskfilter.is_some() || !tags.is_empty() {
+            let tasks = self.list_tasks(taskfilter, None, tags)?;
+            if tasks.is_empty() {
+                return Ok(Vec::new());
+            }
+            ids = tasks.into_iter().map(|t| t.id).collect();
+        }
+        let taskids = helper::get_running_task_ids(conn, &ids)?;
+        let tasks = helper::get_tasks(conn, &taskids)?;
+
+        let now = Timestamp(get_timestamp());
+        let mut result: Vec<RunningTask> = Vec::new();
+        for task in tasks.iter() {
+            let worklog_obj = helper::get_running_worklog(conn, task)?;
+            let wall = seconds_between(&worklog_obj.started, &now)?;
+            let breaks = helper::get_break_time(conn, worklog_obj.id, worklog_obj.started, now)?;
+            result.push(RunningTask {
+                name: task.taskname.to_owned(),
+                spent: helper::get_spent_time(conn, task)?,
+                current_spent: wall - breaks,
+                current_wall: wall,
+                started: worklog_obj.started.0,
+                paused_at: helper::get_open_pause(conn, &worklog_obj)?.map(|p| p.started.0),
+            });
+        }
+        Ok(result)
+    }
+
+    /// List running tasks like `get_running_tasks` without filters, most recently
+    /// started first.
+    ///
+    /// Uses a single query, so it is cheap enough to run from a shell prompt.
+    pub fn list_running(&self) -> Result<Vec<RunningTask>, Error> {
+        use diesel::sql_types::BigInt;
+        let rows = diesel::sql_query(format!(
+            "SELECT task.taskname, worklog.started, \
+                CAST({running} + COALESCE((SELECT SUM(done.duration) FROM worklog AS done \
+                    WHERE done.task_id = worklog.task_id AND done.ignored = 0 \
+                    AND done.stopped IS NOT NULL), 0) AS INTEGER) AS spent, \
+                CAST({running} AS INTEGER) AS current_spent, \
+                CAST(?1 - worklog.started AS INTEGER) AS current_wall, \
+                (SELECT pause.started FROM pause WHERE pause.worklog_id = worklog.id \
+                    AND pause.stopped IS NULL) AS paused_at \
+            FROM worklog INNER JOIN task ON task.id = worklog.task_id \
+            WHERE worklog.stopped IS NULL AND worklog.ignored = 0 \
+            ORDER BY worklog.started DESC",
+            running = helper::RUNNING_SECONDS
+        ))
+        .bind::<BigInt, _>(get_timestamp().timestamp())
+        .load::<models::RunningWorklog>(&self.conn)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| RunningTask {
+                name: row.taskname,
+                spent: row.spent,
+                current_spent: row.current_spent,
+                current_wall: row.current_wall,
+                started: row.started.0,
+                paused_at: row.paused_at.map(|t| t.0),
+            })
+            .collect())
     }
-    Ok(result)
 }
 
 mod helper {
     use super::*;
+    use diesel::connection::TransactionManager;
     use diesel::SqliteConnection;
 
-    pub fn get_task_id(conn: &SqliteConnection, name: &str) -> Result<i32, BoxError> {
+    pub fn get_task_id(conn: &SqliteConnection, name: &str) -> Result<i32, Error> {
+        Ok(get_task(conn, name)?.id)
+    }
+
+    /// Get task `name`, failing on tasks in the trash.
+    pub fn get_task(conn: &SqliteConnection, name: &str) -> Result<models::Task, Error> {
+        match find_task(conn, name)? {
+            Some(taskobj) if taskobj.deleted_at.is_some() => Err(Error::TaskIsTrashed {
+                taskname: taskobj.taskname,
+            }),
+            Some(taskobj) => Ok(taskobj),
+            None => Err(Error::TaskNotFound {
+                taskname: name.to_owned(),
+            }),
+        }
+    }
+
+    /// Find task `name`, including tasks in the trash.
+    pub fn find_task(conn: &SqliteConnection, name: &str) -> Result<Option<models::Task>, Error> {
+        use schema::task::dsl::*;
+        let found_task = task
+            .filter(taskname.eq(name))
+            .first::<models::Task>(conn)
+            .optional()?;
+        Ok(found_task)
+    }
+
+    pub fn get_task_by_id(conn: &SqliteConnection, taskid: i32) -> Result<models::Task, Error> {
         use schema::task::dsl::*;
-        let taskid = task.select(id).filter(taskname.eq(name)).first::<i32>(conn);
-        match taskid {
+        let found_task = task.find(taskid).first::<models::Task>(conn)?;
+        Ok(found_task)
+    }
+
+    pub fn get_worklog(conn: &SqliteConnection, worklog_id: i32) -> Result<models::Worklog, Error> {
+        use schema::worklog::dsl::*;
+        match worklog.find(worklog_id).first::<models::Worklog>(conn) {
             Ok(val) => Ok(val),
+            Err(diesel::result::Error::NotFound) => Err(Error::WorklogNotFound { id: worklog_id }),
             Err(err) => Err(err.into()),
         }
     }
 
-    pub fn get_task(conn: &SqliteConnection, name: &str) -> Result<models::Task, BoxError> {
-        use schema::task::dsl::*;
-        let found_task = task.filter(taskname.eq(name)).first::<models::Task>(conn)?;
-        Ok(found_task)
-    }
-
     /// Get batch task objects from given slice of their id.
-    pub fn get_tasks(conn: &SqliteConnection, ids: &[i32]) -> Result<Vec<models::Task>, BoxError> {
+    pub fn get_tasks(conn: &SqliteConnection, ids: &[i32]) -> Result<Vec<models::Task>, Error> {
         use schema::task::dsl::*;
         let tasks = task.filter(id.eq_any(ids)).load::<models::Task>(conn)?;
         Ok(tasks)
@@ -260,7 +1853,7 @@ mod helper {
     pub fn check_task_is_running(
         conn: &SqliteConnection,
         task: &models::Task,
-    ) -> Result<bool, BoxError> {
+    ) -> Result<bool, Error> {
         use schema::worklog::dsl::*;
         let worklogs: Vec<i32> = models::Worklog::belonging_to(task)
             .filter(stopped.is_null())
@@ -279,7 +1872,7 @@ mod helper {
     pub fn ignore_invalid_worklogs(
         conn: &SqliteConnection,
         task: &models::Task,
-    ) -> Result<(), BoxError> {
+    ) -> Result<(), Error> {
         use schema::worklog::dsl::*;
         let worklogs: Vec<models::Worklog> = models::Worklog::belonging_to(task)
             .filter(stopped.is_null())
@@ -299,9 +1892,20 @@ mod helper {
 
     /// Insert new worklog for given `task_id`.
     ///
+    /// `start` defaults to current time.
     /// This function **does not** check for duplicate running worklog entries.
-    pub fn create_worklog(conn: &SqliteConnection, taskid: i32) -> Result<(), BoxError> {
-        let new_worklog = models::NewWorklog { task_id: taskid };
+    pub fn create_worklog(
+        conn: &SqliteConnection,
+        taskid: i32,
+        start: Option<Timestamp>,
+        message: Option<&str>,
+    ) -> Result<(), Error> {
+        let new_worklog = models::NewWorklog {
+            task_id: taskid,
+            started: start,
+            notes: message,
+            ..Default::default()
+        };
         let result = diesel::insert_into(schema::worklog::table)
             .values(&new_worklog)
             .execute(conn);
@@ -314,39 +1918,241 @@ mod helper {
         }
     }
 
+    /// Insert an already stopped worklog for given `task_id`.
+    pub fn create_stopped_worklog(
+        conn: &SqliteConnection,
+        taskid: i32,
+        start: Timestamp,
+        stop: Timestamp,
+        seconds: i32,
+    ) -> Result<models::Worklog, Error> {
+        let new_worklog = models::NewWorklog {
+            task_id: taskid,
+            started: Some(start),
+            stopped: Some(stop),
+            duration: Some(seconds),
+            ..Default::default()
+        };
+        diesel::insert_into(schema::worklog::table)
+            .values(&new_worklog)
+            .execute(conn)?;
+        use schema::worklog::dsl::*;
+        let created = worklog
+            .filter(task_id.eq(taskid))
+            .filter(started.eq(start))
+            .order(id.desc())
+            .first::<models::Worklog>(conn)?;
+        Ok(created)
+    }
+
+    /// Check whether period from `start` to `stop` overlaps any non-ignored
+    /// worklog of given `taskobj`.
+    ///
+    /// Running worklogs and a `None` value of `stop` are treated as open ended.
+    /// Worklog with id `exclude` is left out of the check.
+    pub fn check_worklog_overlap(
+        conn: &SqliteConnection,
+        taskobj: &models::Task,
+        start: Timestamp,
+        stop: Option<Timestamp>,
+        exclude: Option<i32>,
+    ) -> Result<bool, Error> {
+        use schema::worklog::dsl::*;
+        let mut query = models::Worklog::belonging_to(taskobj)
+            .filter(ignored.eq(false))
+            .filter(stopped.is_null().or(stopped.gt(start)))
+            .into_boxed();
+        if let Some(stop) = stop {
+            query = query.filter(started.lt(stop));
+        }
+        if let Some(exclude) = exclude {
+            query = query.filter(id.ne(exclude));
+        }
+        let overlaps = query.select(id).load::<i32>(conn)?;
+        Ok(!overlaps.is_empty())
+    }
+
+    /// Keep worklogs of `source` from overlapping those of `target` before they are
+    /// merged.
+    ///
+    /// A worklog overlapping one of `target` ends when that one starts, or is
+    /// ignored when it starts inside it. Returns the number of adjusted worklogs.
+    pub fn fit_worklogs(
+        conn: &SqliteConnection,
+        source: &models::Task,
+        target: &models::Task,
+    ) -> Result<usize, Error> {
+        use schema::worklog::dsl::*;
+        let targets = models::Worklog::belonging_to(target)
+            .filter(ignored.eq(false))
+            .order(started.asc())
+            .load::<models::Worklog>(conn)?;
+        let sources = models::Worklog::belonging_to(source)
+            .filter(ignored.eq(false))
+            .load::<models::Worklog>(conn)?;
+        let mut adjusted = 0;
+        for worklog_obj in sources.iter() {
+            let other = targets.iter().find(|other| {
+                !matches!(other.stopped, Some(stop) if stop <= worklog_obj.started)
+                    && !matches!(worklog_obj.stopped, Some(stop) if stop <= other.started)
+            });
+            let stop = match other {
+                Some(other) if other.started > worklog_obj.started => other.started,
+                Some(_) => {
+                    diesel::update(worklog_obj)
+                        .set(ignored.eq(true))
+                        .execute(conn)?;
+                    adjusted += 1;
+                    continue;
+                }
+                None => continue,
+            };
+            if let Some(pause_obj) = get_open_pause(conn, worklog_obj)? {
+                diesel::update(&pause_obj)
+                    .set(schema::pause::stopped.eq(std::cmp::max(pause_obj.started, stop)))
+                    .execute(conn)?;
+            }
+            let seconds = seconds_between(&worklog_obj.started, &stop)?
+                - get_break_time(conn, worklog_obj.id, worklog_obj.started, stop)?;
+            diesel::update(worklog_obj)
+                .set((stopped.eq(stop), duration.eq(seconds)))
+                .execute(conn)?;
+            adjusted += 1;
+        }
+        Ok(adjusted)
+    }
+
     /// Update `stopped` and `duration` field for running worklog for given `taskobj`.
     ///
+    /// `stop` defaults to current time. It must not be earlier than the worklog's
+    /// `started`. `message` is appended to the worklog's notes. An open break ends
+    /// at `stop` and breaks are not counted in `duration`.
     /// This function **doues not** check for duplicate running worklog entries.
-    pub fn stop_worklog(conn: &SqliteConnection, taskobj: &models::Task) -> Result<(), BoxError> {
+    pub fn stop_worklog(
+        conn: &SqliteConnection,
+        taskobj: &models::Task,
+        stop: Option<Timestamp>,
+        message: Option<&str>,
+    ) -> Result<(), Error> {
         use schema::worklog::dsl::*;
         let worklog_obj = self::get_running_worklog(conn, taskobj)?;
-        let stop_timestamp = Utc::now().naive_local();
-        let start_timestamp =
-            NaiveDateTime::parse_from_str(&worklog_obj.started, "%Y-%m-%d %H:%M:%S")?;
-        let spent_seconds: i64 = stop_timestamp
-            .signed_duration_since(start_timestamp)
-            .num_seconds();
-
-        let seconds = i32::try_from(spent_seconds)?;
+        let stop_timestamp = stop.unwrap_or_else(|| Timestamp(get_timestamp()));
+        let seconds = seconds_between(&worklog_obj.started, &stop_timestamp)?;
+        if seconds < 0 {
+            return Err(Error::InvalidTimeRange);
+        }
+        if let Some(pause_obj) = get_open_pause(conn, &worklog_obj)? {
+            if stop_timestamp < pause_obj.started {
+                return Err(Error::InvalidTimeRange);
+            }
+            diesel::update(&pause_obj)
+                .set(schema::pause::stopped.eq(stop_timestamp))
+                .execute(conn)?;
+        }
+        let seconds =
+            seconds - get_break_time(conn, worklog_obj.id, worklog_obj.started, stop_timestamp)?;
+        let new_notes = match (worklog_obj.notes.as_deref(), message) {
+            (Some(current), Some(message)) => Some(format!("{}; {}", current, message)),
+            (current, message) => current.or(message).map(String::from),
+        };
+
         diesel::update(&worklog_obj)
             .set((
-                stopped.eq(stop_timestamp.format("%Y-%m-%d %H:%M:%S").to_string()),
+                stopped.eq(stop_timestamp),
                 duration.eq(seconds),
+                notes.eq(new_notes),
             ))
             .execute(conn)?;
         Ok(())
     }
 
-    pub fn flag_complete(conn: &SqliteConnection, taskobj: &models::Task) -> Result<(), BoxError> {
+    /// Flag task complete, renewing it when it recurs.
+    ///
+    /// Returns the next instance of a recurring task.
+    pub fn flag_complete(
+        conn: &SqliteConnection,
+        taskobj: &models::Task,
+    ) -> Result<Option<models::Task>, Error> {
         if taskobj.done {
-            return Ok(());
+            return Ok(None);
         }
         let mut updates = taskobj.create_changeset();
         updates.done = Some(true);
-        match diesel::update(&updates).set(&updates).execute(conn) {
-            Ok(_val) => Ok(()),
-            Err(err) => Err(err.into()),
+        in_transaction(conn, false, || {
+            diesel::update(&updates).set(&updates).execute(conn)?;
+            renew_recurring(conn, taskobj)
+        })
+    }
+
+    /// Due date of `taskobj`, today when it has none.
+    pub fn get_due_date(taskobj: &models::Task) -> Result<NaiveDate, Error> {
+        match &taskobj.duedate {
+            Some(text) => {
+                NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| Error::InvalidTimestamp {
+                    value: text.to_owned(),
+                })
+            }
+            None => Ok(Local::today().naive_local()),
+        }
+    }
+
+    /// Renew completed recurring task `taskobj`.
+    ///
+    /// The completed task is kept as `name@<due date>` without a rule, outside
+    /// the subtasks of `name`, and a new task `name` with its notes, allocated
+    /// time, tags and rule is created, due on the next date of the rule that is
+    /// not in the past. Returns the new task, `None` when `taskobj` does not recur.
+    pub fn renew_recurring(
+        conn: &SqliteConnection,
+        taskobj: &models::Task,
+    ) -> Result<Option<models::Task>, Error> {
+        use schema::task::dsl::*;
+        let rule = match &taskobj.recur {
+            Some(text) => text.parse::<Recurrence>()?,
+            None => return Ok(None),
+        };
+        let today = Local::today().naive_local();
+        let due = get_due_date(taskobj)?;
+        // rules stored before pinning keep the day of this instance
+        let rule = rule.pinned(due);
+        let next_due = rule.next_due(due, today).to_string();
+
+        let mut archived = format!("{}@{}", taskobj.taskname, due);
+        let mut suffix = 1;
+        while find_task(conn, &archived)?.is_some() {
+            suffix += 1;
+            archived = format!("{}@{}-{}", taskobj.taskname, due, suffix);
+        }
+        diesel::update(taskobj)
+            .set((taskname.eq(&archived), recur.eq(None::<String>)))
+            .execute(conn)?;
+
+        let rule_text = rule.to_string();
+        let new_task = models::NewTask {
+            taskname: &taskobj.taskname,
+            notes: taskobj.notes.as_deref(),
+            allocated: Some(taskobj.allocated),
+            duedate: Some(&next_due),
+            recur: Some(&rule_text),
+            ..Default::default()
+        };
+        diesel::insert_into(task).values(&new_task).execute(conn)?;
+        let renewed = get_task(conn, &taskobj.taskname)?;
+
+        let tag_ids = schema::task_tag::table
+            .filter(schema::task_tag::task_id.eq(taskobj.id))
+            .select(schema::task_tag::tag_id)
+            .load::<i32>(conn)?;
+        for tagid in tag_ids {
+            let link = models::TaskTag {
+                task_id: renewed.id,
+                tag_id: tagid,
+            };
+            diesel::insert_into(schema::task_tag::table)
+                .values(&link)
+                .execute(conn)?;
         }
+        Ok(Some(renewed))
     }
 
     /// Get id of all running tasks.
@@ -356,7 +2162,7 @@ mod helper {
     pub fn get_running_task_ids(
         conn: &SqliteConnection,
         filteredtasks: &[i32],
-    ) -> Result<Vec<i32>, BoxError> {
+    ) -> Result<Vec<i32>, Error> {
         use schema::worklog::dsl::*;
         let mut runnings = worklog.into_boxed();
         runnings = runnings.filter(stopped.is_null()).filter(ignored.eq(false));
@@ -369,67 +2175,409 @@ mod helper {
         }
     }
 
-    /// Get `started` timestamp of given running `taskobj`.
+    pub fn get_spent_time(conn: &SqliteConnection, taskobj: &models::Task) -> Result<i32, Error> {
+        let rows = get_tasks_with_spent(conn, None, None, Some(taskobj.id), false)?;
+        Ok(rows.first().map_or(0, |row| row.spent))
+    }
+
+    /// SQL expression for seconds of a running `worklog` up to `?1`, without breaks.
+    pub const RUNNING_SECONDS: &str = "(?1 - worklog.started - COALESCE((\
+        SELECT SUM(COALESCE(pause.stopped, ?1) - pause.started) \
+        FROM pause WHERE pause.worklog_id = worklog.id), 0))";
+
+    /// Load tasks with durations of their non-ignored worklogs summed up.
     ///
-    /// Task in argument must be a running task
-    pub fn get_started_time(
+    /// Filters by name the same way as `list_tasks`, by `done` flag and by `id`
+    /// when given. Loads tasks in the trash instead of the others with `trashed`.
+    /// Running worklogs count up to now.
+    pub fn get_tasks_with_spent(
         conn: &SqliteConnection,
-        taskobj: &models::Task,
-    ) -> Result<String, BoxError> {
-        let worklog_obj = get_running_worklog(conn, taskobj)?;
-        Ok(worklog_obj.started)
+        taskfilter: Option<&str>,
+        done: Option<bool>,
+        taskid: Option<i32>,
+        trashed: bool,
+    ) -> Result<Vec<models::TaskSpent>, Error> {
+        use diesel::sql_types::{BigInt, Bool, Integer, Nullable, Text};
+        let rows = diesel::sql_query(format!(
+            "SELECT task.*, \
+                CAST(COALESCE(SUM(CASE WHEN worklog.stopped IS NULL \
+                    THEN {running} \
+                    ELSE worklog.duration END), 0) AS INTEGER) AS spent, \
+                CAST(COALESCE(SUM(CASE WHEN worklog.stopped IS NULL \
+                    THEN {running} \
+                    END), 0) AS INTEGER) AS current_spent \
+            FROM task LEFT JOIN worklog \
+                ON worklog.task_id = task.id AND worklog.ignored = 0 \
+            WHERE (?2 IS NULL OR task.taskname LIKE ?2 OR task.taskname LIKE ?2 || '::%') \
+                AND (?3 IS NULL OR task.done = ?3) \
+                AND (?4 IS NULL OR task.id = ?4) \
+                AND (task.deleted_at IS NOT NULL) = ?5 \
+            GROUP BY task.id \
+            ORDER BY task.id",
+            running = RUNNING_SECONDS
+        ))
+        .bind::<BigInt, _>(get_timestamp().timestamp())
+        .bind::<Nullable<Text>, _>(taskfilter)
+        .bind::<Nullable<Bool>, _>(done)
+        .bind::<Nullable<Integer>, _>(taskid)
+        .bind::<Bool, _>(trashed)
+        .load::<models::TaskSpent>(conn)?;
+        Ok(rows)
     }
 
-    pub fn get_spent_time(
+    /// Check whether task `taskid` has a worklog started at `start`.
+    pub fn check_worklog_exists(
         conn: &SqliteConnection,
-        taskobj: &models::Task,
-    ) -> Result<i32, BoxError> {
+        taskid: i32,
+        start: Timestamp,
+    ) -> Result<bool, Error> {
+        use diesel::dsl::{exists, select};
         use schema::worklog::dsl::*;
-        let spents = models::Worklog::belonging_to(taskobj)
-            .filter(stopped.is_not_null())
-            .filter(ignored.eq(false))
-            .select(duration)
+        let found = select(exists(
+            worklog.filter(task_id.eq(taskid)).filter(started.eq(start)),
+        ))
+        .get_result(conn)?;
+        Ok(found)
+    }
+
+    /// Open an operation of the undo log, returning its id.
+    ///
+    /// Operations left open by an interrupted command are closed first, so later
+    /// changes are not logged into them.
+    pub fn open_undo_op(conn: &SqliteConnection, command: &str) -> Result<i32, Error> {
+        use schema::undo_op;
+        let stale = undo_op::table
+            .select(undo_op::id)
+            .filter(undo_op::open.eq(true))
             .load::<i32>(conn)?;
+        for opid in stale {
+            close_undo_op(conn, opid)?;
+        }
+        let new_op = models::NewUndoOp {
+            created: get_timestamp().into(),
+            command,
+        };
+        diesel::insert_into(undo_op::table)
+            .values(&new_op)
+            .execute(conn)?;
+        let opid = undo_op::table
+            .select(undo_op::id)
+            .order(undo_op::id.desc())
+            .first::<i32>(conn)?;
+        Ok(opid)
+    }
 
-        let current_spent = get_current_spent_time(conn, taskobj)?;
-        // manual addition
-        Ok(spents.iter().sum::<i32>() + current_spent)
+    /// Close operation `opid` of the undo log, dropping it when nothing was logged
+    /// and operations beyond `UNDO_HISTORY`.
+    pub fn close_undo_op(conn: &SqliteConnection, opid: i32) -> Result<(), Error> {
+        use diesel::dsl::{exists, select};
+        use schema::{undo_log, undo_op};
+        let logged: bool =
+            select(exists(undo_log::table.filter(undo_log::op_id.eq(opid)))).get_result(conn)?;
+        if logged {
+            diesel::update(undo_op::table.find(opid))
+                .set(undo_op::open.eq(false))
+                .execute(conn)?;
+        } else {
+            diesel::delete(undo_op::table.find(opid)).execute(conn)?;
+        }
+        let expired = undo_op::table
+            .select(undo_op::id)
+            .filter(undo_op::open.eq(false))
+            .order(undo_op::id.desc())
+            .limit(-1) // SQLite takes no OFFSET without LIMIT
+            .offset(UNDO_HISTORY)
+            .load::<i32>(conn)?;
+        if !expired.is_empty() {
+            diesel::delete(undo_log::table.filter(undo_log::op_id.eq_any(&expired)))
+                .execute(conn)?;
+            diesel::delete(undo_op::table.filter(undo_op::id.eq_any(&expired))).execute(conn)?;
+        }
+        Ok(())
     }
 
-    pub fn get_current_spent_time(
+    /// Load the latest `limit` closed operations of the undo log with their row
+    /// changes in order, latest operation first.
+    pub fn load_undo_ops(
         conn: &SqliteConnection,
-        taskobj: &models::Task,
-    ) -> Result<i32, BoxError> {
-        use schema::worklog::dsl::*;
-        let current_spent = if helper::check_task_is_running(&conn, &taskobj)? {
-            let running = models::Worklog::belonging_to(taskobj)
-                .filter(stopped.is_null())
-                .filter(ignored.eq(false))
-                .select(started)
-                .first::<String>(conn)?;
-            let stop_timestamp = Utc::now().naive_local();
-            let start_timestamp = NaiveDateTime::parse_from_str(&running, "%Y-%m-%d %H:%M:%S")?;
-            i32::try_from(
-                stop_timestamp
-                    .signed_duration_since(start_timestamp)
-                    .num_seconds(),
-            )?
-        } else {
-            0
-        };
-        Ok(current_spent)
+        limit: Option<i64>,
+    ) -> Result<Vec<(models::UndoOp, Vec<models::UndoLog>)>, Error> {
+        use schema::{undo_log, undo_op};
+        let mut query = undo_op::table
+            .filter(undo_op::open.eq(false))
+            .order(undo_op::id.desc())
+            .into_boxed();
+        if let Some(limit) = limit {
+            query = query.limit(limit);
+        }
+        let ops = query.load::<models::UndoOp>(conn)?;
+        let logs = models::UndoLog::belonging_to(&ops)
+            .order(undo_log::id)
+            .load::<models::UndoLog>(conn)?
+            .grouped_by(&ops);
+        Ok(ops.into_iter().zip(logs).collect())
     }
 
-    pub fn delete_worklogs(conn: &SqliteConnection, taskid: i32) -> Result<(), BoxError> {
-        use schema::worklog::dsl::*;
-        diesel::delete(worklog.filter(task_id.eq(taskid))).execute(conn)?;
+    /// Key columns of `table` in the undo log.
+    fn undo_keys(table: &str) -> Result<&'static [&'static str], Error> {
+        UNDO_TABLES
+            .iter()
+            .find(|(name, _)| *name == table)
+            .map(|(_, keys)| *keys)
+            .ok_or_else(|| Error::InvalidData(format!("undo log of unknown table {}", table)))
+    }
+
+    /// Parse a row image of the undo log into its columns.
+    fn undo_row(image: &str) -> Result<serde_json::Map<String, serde_json::Value>, Error> {
+        let row: serde_json::Map<String, serde_json::Value> = serde_json::from_str(image)?;
+        // column names are put into SQL
+        if let Some(column) = row
+            .keys()
+            .find(|c| !c.chars().all(|ch| ch.is_ascii_lowercase() || ch == '_'))
+        {
+            return Err(Error::InvalidData(format!(
+                "undo log of unknown column {}",
+                column
+            )));
+        }
+        Ok(row)
+    }
+
+    /// Describe the row changes of operation `op`.
+    pub fn describe_undo_op(
+        op: &models::UndoOp,
+        logs: &[models::UndoLog],
+    ) -> Result<UndoOperation, Error> {
+        let mut changes = Vec::with_capacity(logs.len());
+        for log in logs.iter() {
+            let before = log.before.as_deref().map(undo_row).transpose()?;
+            let after = log.after.as_deref().map(undo_row).transpose()?;
+            let (kind, row) = match (&before, &after) {
+                (None, Some(row)) => (ChangeKind::Created, row),
+                (Some(row), None) => (ChangeKind::Deleted, row),
+                (Some(_), Some(row)) => (ChangeKind::Updated, row),
+                (None, None) => continue,
+            };
+            let keys = undo_keys(&log.tablename)?;
+            let key = match keys {
+                ["id"] => format!("#{}", row["id"]),
+                _ => keys
+                    .iter()
+                    .map(|k| format!("{} {}", k, row[*k]))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            };
+            let columns = match (&before, &after) {
+                (Some(before), Some(after)) => after
+                    .iter()
+                    .filter(|(column, value)| before.get(*column) != Some(value))
+                    .map(|(column, _)| column.to_owned())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            changes.push(UndoChange {
+                table: log.tablename.to_owned(),
+                key,
+                kind,
+                columns,
+            });
+        }
+        Ok(UndoOperation {
+            id: op.id,
+            created: op.created.0,
+            command: op.command.to_owned(),
+            changes,
+        })
+    }
+
+    /// Revert a row change of the undo log, restoring the row image before it.
+    pub fn revert_change(conn: &SqliteConnection, log: &models::UndoLog) -> Result<(), Error> {
+        use diesel::sql_types::Text;
+        let keys = undo_keys(&log.tablename)?;
+        // `?1` is the row image the key columns are taken from
+        let key_filter = keys
+            .iter()
+            .map(|k| format!("{k} = json_extract(?1, '$.{k}')", k = k))
+            .collect::<Vec<String>>()
+            .join(" AND ");
+        match (&log.before, &log.after) {
+            (None, Some(after)) => {
+                diesel::sql_query(format!(
+                    "DELETE FROM {} WHERE {}",
+                    log.tablename, key_filter
+                ))
+                .bind::<Text, _>(after)
+                .execute(conn)?;
+            }
+            (Some(before), None) => {
+                let columns: Vec<String> = undo_row(before)?.keys().cloned().collect();
+                let values: Vec<String> = columns
+                    .iter()
+                    .map(|c| format!("json_extract(?1, '$.{}')", c))
+                    .collect();
+                diesel::sql_query(format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    log.tablename,
+                    columns.join(", "),
+                    values.join(", ")
+                ))
+                .bind::<Text, _>(before)
+                .execute(conn)?;
+            }
+            (Some(before), Some(after)) => {
+                let assignments: Vec<String> = undo_row(before)?
+                    .keys()
+                    .map(|c| format!("{c} = json_extract(?2, '$.{c}')", c = c))
+                    .collect();
+                diesel::sql_query(format!(
+                    "UPDATE {} SET {} WHERE {}",
+                    log.tablename,
+                    assignments.join(", "),
+                    key_filter
+                ))
+                .bind::<Text, _>(after)
+                .bind::<Text, _>(before)
+                .execute(conn)?;
+            }
+            (None, None) => (),
+        }
+        Ok(())
+    }
+
+    /// Run `f` in a transaction, rolling it back on error or when `rollback` is set.
+    pub fn in_transaction<T>(
+        conn: &SqliteConnection,
+        rollback: bool,
+        f: impl FnOnce() -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let manager = conn.transaction_manager();
+        manager.begin_transaction(conn)?;
+        match f() {
+            Ok(value) => {
+                if rollback {
+                    manager.rollback_transaction(conn)?;
+                } else {
+                    manager.commit_transaction(conn)?;
+                }
+                Ok(value)
+            }
+            Err(err) => {
+                manager.rollback_transaction(conn)?;
+                Err(err)
+            }
+        }
+    }
+
+    pub fn delete_worklogs(conn: &SqliteConnection, taskid: i32) -> Result<(), Error> {
+        use schema::{pause, worklog};
+        let worklog_ids = worklog::table
+            .filter(worklog::task_id.eq(taskid))
+            .select(worklog::id);
+        diesel::delete(pause::table.filter(pause::worklog_id.eq_any(worklog_ids))).execute(conn)?;
+        diesel::delete(worklog::table.filter(worklog::task_id.eq(taskid))).execute(conn)?;
+        Ok(())
+    }
+
+    /// Get the break of `worklog_obj` that has not been resumed yet.
+    pub fn get_open_pause(
+        conn: &SqliteConnection,
+        worklog_obj: &models::Worklog,
+    ) -> Result<Option<models::Pause>, Error> {
+        use schema::pause::dsl::*;
+        let found = models::Pause::belonging_to(worklog_obj)
+            .filter(stopped.is_null())
+            .first::<models::Pause>(conn)
+            .optional()?;
+        Ok(found)
+    }
+
+    /// Seconds of breaks of worklog `worklogid` between `from` and `until`.
+    ///
+    /// A break that has not been resumed lasts until `until`.
+    pub fn get_break_time(
+        conn: &SqliteConnection,
+        worklogid: i32,
+        from: Timestamp,
+        until: Timestamp,
+    ) -> Result<i32, Error> {
+        use schema::pause::dsl::*;
+        let pauses = pause
+            .filter(worklog_id.eq(worklogid))
+            .load::<models::Pause>(conn)?;
+        let mut total = 0;
+        for pause_obj in pauses.iter() {
+            let start = std::cmp::max(pause_obj.started, from);
+            let stop = std::cmp::min(pause_obj.stopped.unwrap_or(until), until);
+            if stop > start {
+                total += seconds_between(&start, &stop)?;
+            }
+        }
+        Ok(total)
+    }
+
+    pub fn get_tag_id(conn: &SqliteConnection, tagname: &str) -> Result<i32, Error> {
+        use schema::tag::dsl::*;
+        tag.filter(name.eq(tagname))
+            .select(id)
+            .first::<i32>(conn)
+            .optional()?
+            .ok_or_else(|| Error::TagNotFound {
+                tag: tagname.to_owned(),
+            })
+    }
+
+    pub fn get_or_create_tag_id(conn: &SqliteConnection, tagname: &str) -> Result<i32, Error> {
+        match get_tag_id(conn, tagname) {
+            Err(Error::TagNotFound { .. }) => {
+                diesel::insert_into(schema::tag::table)
+                    .values(&models::NewTag { name: tagname })
+                    .execute(conn)?;
+                get_tag_id(conn, tagname)
+            }
+            result => result,
+        }
+    }
+
+    /// Ids of tasks having all of `tags`, `None` when `tags` is empty.
+    ///
+    /// Fails on tags that do not exist.
+    pub fn get_tagged_task_ids(
+        conn: &SqliteConnection,
+        tags: &[String],
+    ) -> Result<Option<Vec<i32>>, Error> {
+        use schema::task_tag::dsl::*;
+        let mut ids: Option<Vec<i32>> = None;
+        for tagname in tags.iter() {
+            let tagged = task_tag
+                .filter(tag_id.eq(get_tag_id(conn, tagname)?))
+                .select(task_id)
+                .load::<i32>(conn)?;
+            ids = Some(match ids {
+                Some(ids) => ids.into_iter().filter(|i| tagged.contains(i)).collect(),
+                None => tagged,
+            });
+        }
+        Ok(ids)
+    }
+
+    pub fn delete_task_tags(conn: &SqliteConnection, taskid: i32) -> Result<(), Error> {
+        use schema::task_tag::dsl::*;
+        diesel::delete(task_tag.filter(task_id.eq(taskid))).execute(conn)?;
+        Ok(())
+    }
+
+    /// Delete tags that are not attached to any task.
+    pub fn prune_tags(conn: &SqliteConnection) -> Result<(), Error> {
+        use schema::{tag, task_tag};
+        let used = task_tag::table.select(task_tag::tag_id);
+        diesel::delete(tag::table.filter(tag::id.ne_all(used))).execute(conn)?;
         Ok(())
     }
 
-    fn get_running_worklog(
+    pub fn get_running_worklog(
         conn: &SqliteConnection,
         taskobj: &models::Task,
-    ) -> Result<models::Worklog, BoxError> {
+    ) -> Result<models::Worklog, Error> {
         use schema::worklog::dsl::*;
         let started_data = models::Worklog::belonging_to(taskobj)
             .filter(stopped.is_null())
@@ -442,12 +2590,13 @@ mod helper {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::establish_connection;
+    use crate::utils::parse_timestamp;
+    use chrono::Duration;
     use std::path::PathBuf;
     use std::{thread, time};
     use tempfile::TempDir;
 
-    fn setup() -> Result<(TempDir, PathBuf), BoxError> {
+    fn setup() -> Result<(TempDir, PathBuf), Error> {
         let tempdir = TempDir::new()?;
         let mut dbpath = PathBuf::new();
         dbpath.push(tempdir.path());
@@ -455,18 +2604,24 @@ mod tests {
         Ok((tempdir, dbpath))
     }
 
+    /// Parse a UTC timestamp.
+    fn ts(text: &str) -> Timestamp {
+        Timestamp(DateTime::from_utc(parse_timestamp(text).unwrap(), Utc))
+    }
+
     fn create_task(
         conn: &SqliteConnection,
         taskname: &str,
         notes: Option<&str>,
         allocated: Option<i32>,
         duedate: Option<&str>,
-    ) -> Result<(), BoxError> {
+    ) -> Result<(), Error> {
         let new_task = models::NewTask {
             taskname,
             notes,
             allocated,
             duedate,
+            ..Default::default()
         };
 
         let result = diesel::insert_into(schema::task::table)
@@ -484,55 +2639,53 @@ mod tests {
     }
 
     #[test]
-    fn create_worklog() -> Result<(), BoxError> {
-        let (_tempdir, dbpath) = setup()?;
-        let conn_str = dbpath.to_string_lossy().to_string();
-        let conn = establish_connection(&conn_str)?;
-        self::create_task(&conn, "task1", None, None, None)?;
-        let taskobj = helper::get_task(&conn, "task1")?;
+    fn create_worklog() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        self::create_task(conn, "task1", None, None, None)?;
+        let taskobj = helper::get_task(conn, "task1")?;
 
-        assert_eq!(helper::check_task_is_running(&conn, &taskobj)?, false);
-        helper::create_worklog(&conn, taskobj.id)?;
-        assert_eq!(helper::check_task_is_running(&conn, &taskobj)?, true);
+        assert_eq!(helper::check_task_is_running(conn, &taskobj)?, false);
+        helper::create_worklog(conn, taskobj.id, None, None)?;
+        assert_eq!(helper::check_task_is_running(conn, &taskobj)?, true);
 
         Ok(())
     }
 
     #[test]
-    fn ignore_multiple_worklogs() -> Result<(), BoxError> {
-        let (_tempdir, dbpath) = setup()?;
-        let conn_str = dbpath.to_string_lossy().to_string();
-        let conn = establish_connection(&conn_str)?;
+    fn ignore_multiple_worklogs() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
 
-        self::create_task(&conn, "task1", None, None, None)?;
-        self::create_task(&conn, "task2", None, None, None)?;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
 
-        let taskobj = helper::get_task(&conn, "task1")?;
+        let taskobj = helper::get_task(conn, "task1")?;
 
-        helper::create_worklog(&conn, taskobj.id)?;
+        helper::create_worklog(conn, taskobj.id, None, None)?;
         self::pause(1100);
-        helper::create_worklog(&conn, taskobj.id)?;
+        helper::create_worklog(conn, taskobj.id, None, None)?;
 
         use schema::worklog::dsl::*;
         let worklogs: Vec<models::Worklog> = models::Worklog::belonging_to(&taskobj)
             .filter(stopped.is_null())
             .filter(ignored.eq(false))
             .order(started.desc())
-            .load::<models::Worklog>(&conn)?;
+            .load::<models::Worklog>(conn)?;
 
         debug!("{:?}", taskobj);
-        debug!("{}", helper::check_task_is_running(&conn, &taskobj)?);
+        debug!("{}", helper::check_task_is_running(conn, &taskobj)?);
         debug!("{:?}", worklogs);
 
         assert_eq!(worklogs.len(), 2);
 
-        helper::ignore_invalid_worklogs(&conn, &taskobj)?;
+        helper::ignore_invalid_worklogs(conn, &taskobj)?;
 
         let worklogs = models::Worklog::belonging_to(&taskobj)
             .filter(stopped.is_null())
             .filter(ignored.eq(false))
             .order(started.desc())
-            .load::<models::Worklog>(&conn)?;
+            .load::<models::Worklog>(conn)?;
 
         assert_eq!(worklogs.len(), 1);
 
@@ -540,85 +2693,82 @@ mod tests {
     }
 
     #[test]
-    fn stop_worklog() -> Result<(), BoxError> {
-        let (_tempdir, dbpath) = setup()?;
-        let conn_str = dbpath.to_string_lossy().to_string();
-        let conn = establish_connection(&conn_str)?;
+    fn stop_worklog() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
 
-        self::create_task(&conn, "task1", None, None, None)?;
-        self::create_task(&conn, "task2", None, None, None)?;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
 
-        let taskobj = helper::get_task(&conn, "task1")?;
+        let taskobj = helper::get_task(conn, "task1")?;
 
-        helper::create_worklog(&conn, taskobj.id)?;
+        helper::create_worklog(conn, taskobj.id, None, None)?;
 
         use schema::worklog::dsl::*;
         let worklogs = models::Worklog::belonging_to(&taskobj)
             .filter(stopped.is_null())
             .filter(ignored.eq(false))
             .order(started.desc())
-            .load::<models::Worklog>(&conn)?;
+            .load::<models::Worklog>(conn)?;
         assert_eq!(worklogs.len(), 1);
 
-        helper::stop_worklog(&conn, &taskobj)?;
+        helper::stop_worklog(conn, &taskobj, None, None)?;
         let worklogs = models::Worklog::belonging_to(&taskobj)
             .filter(stopped.is_null())
             .filter(ignored.eq(false))
             .order(started.desc())
-            .load::<models::Worklog>(&conn)?;
+            .load::<models::Worklog>(conn)?;
         assert_eq!(worklogs.len(), 0);
 
         Ok(())
     }
 
     #[test]
-    fn get_running_tasks_ids() -> Result<(), BoxError> {
-        let (_tempdir, dbpath) = setup()?;
-        let conn_str = dbpath.to_string_lossy().to_string();
-        let conn = establish_connection(&conn_str)?;
+    fn get_running_tasks_ids() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
 
-        self::create_task(&conn, "task1", None, None, None)?;
-        self::create_task(&conn, "task2", None, None, None)?;
-        self::create_task(&conn, "task1::abc", None, None, None)?;
-        self::create_task(&conn, "task3", None, None, None)?;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        self::create_task(conn, "task1::abc", None, None, None)?;
+        self::create_task(conn, "task3", None, None, None)?;
 
-        let task1 = helper::get_task(&conn, "task1")?;
-        let task2 = helper::get_task(&conn, "task2")?;
-        let subtask1 = helper::get_task(&conn, "task1::abc")?;
+        let task1 = helper::get_task(conn, "task1")?;
+        let task2 = helper::get_task(conn, "task2")?;
+        let subtask1 = helper::get_task(conn, "task1::abc")?;
 
-        helper::create_worklog(&conn, task1.id)?;
-        helper::create_worklog(&conn, task2.id)?;
+        helper::create_worklog(conn, task1.id, None, None)?;
+        helper::create_worklog(conn, task2.id, None, None)?;
 
         assert_eq!(
             vec![task1.id, task2.id],
-            helper::get_running_task_ids(&conn, &vec![])?
+            helper::get_running_task_ids(conn, &vec![])?
         );
         assert_eq!(
             vec![task1.id],
-            helper::get_running_task_ids(&conn, &vec![task1.id, subtask1.id])?
+            helper::get_running_task_ids(conn, &vec![task1.id, subtask1.id])?
         );
 
         Ok(())
     }
 
     #[test]
-    fn delete_worklogs() -> Result<(), BoxError> {
-        let (_tempdir, dbpath) = setup()?;
-        let conn_str = dbpath.to_string_lossy().to_string();
-        let conn = establish_connection(&conn_str)?;
+    fn delete_worklogs() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
 
-        self::create_task(&conn, "task1", None, None, None)?;
-        let task1 = helper::get_task(&conn, "task1")?;
-        helper::create_worklog(&conn, task1.id)?;
+        self::create_task(conn, "task1", None, None, None)?;
+        let task1 = helper::get_task(conn, "task1")?;
+        helper::create_worklog(conn, task1.id, None, None)?;
 
-        helper::delete_worklogs(&conn, task1.id)?;
+        helper::delete_worklogs(conn, task1.id)?;
 
         use schema::worklog::dsl::*;
         let worklogs = models::Worklog::belonging_to(&task1)
             .filter(stopped.is_null())
             .filter(ignored.eq(false))
             .order(started.desc())
-            .load::<models::Worklog>(&conn)?;
+            .load::<models::Worklog>(conn)?;
 
         assert_eq!(worklogs.is_empty(), true);
 
@@ -626,29 +2776,839 @@ mod tests {
     }
 
     #[test]
-    fn complete_task_on_stop() -> Result<(), BoxError> {
+    fn worklog_overlap() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+
+        self::create_task(conn, "task1", None, None, None)?;
+        let task1 = helper::get_task(conn, "task1")?;
+        helper::create_stopped_worklog(
+            conn,
+            task1.id,
+            ts("2020-05-10 09:00:00"),
+            ts("2020-05-10 10:00:00"),
+            3600,
+        )?;
+
+        let check = |start, stop| helper::check_worklog_overlap(conn, &task1, start, stop, None);
+        assert!(!check(
+            ts("2020-05-10 08:00:00"),
+            Some(ts("2020-05-10 09:00:00"))
+        )?);
+        assert!(!check(
+            ts("2020-05-10 10:00:00"),
+            Some(ts("2020-05-10 11:00:00"))
+        )?);
+        assert!(check(
+            ts("2020-05-10 09:30:00"),
+            Some(ts("2020-05-10 11:00:00"))
+        )?);
+        assert!(check(
+            ts("2020-05-10 08:00:00"),
+            Some(ts("2020-05-10 12:00:00"))
+        )?);
+        assert!(check(ts("2020-05-10 08:00:00"), None)?);
+        assert!(!check(ts("2020-05-10 11:00:00"), None)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unignore_second_running_worklog() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+
+        self::create_task(conn, "task1", None, None, None)?;
+        let task1 = helper::get_task(conn, "task1")?;
+        helper::create_worklog(conn, task1.id, Some(ts("2020-05-10 09:00:00")), None)?;
+        helper::create_worklog(conn, task1.id, Some(ts("2020-05-10 10:00:00")), None)?;
+        helper::ignore_invalid_worklogs(conn, &task1)?;
+
+        use schema::worklog::dsl::*;
+        let ignored_id = worklog
+            .filter(ignored.eq(true))
+            .select(id)
+            .first::<i32>(conn)?;
+        assert!(tracker.set_worklog_ignored(ignored_id, false).is_err());
+
+        helper::stop_worklog(conn, &task1, Some(ts("2020-05-10 11:00:00")), None)?;
+        assert!(tracker.set_worklog_ignored(ignored_id, false).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tasks_with_spent() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task1::sub", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        let task1 = helper::get_task(conn, "task1")?;
+        let subtask = helper::get_task(conn, "task1::sub")?;
+        helper::create_stopped_worklog(
+            conn,
+            task1.id,
+            ts("2020-05-10 09:00:00"),
+            ts("2020-05-10 10:00:00"),
+            3600,
+        )?;
+        helper::create_stopped_worklog(
+            conn,
+            task1.id,
+            ts("2020-05-10 11:00:00"),
+            ts("2020-05-10 11:30:00"),
+            1800,
+        )?;
+        helper::create_worklog(
+            conn,
+            task1.id,
+            Some(Timestamp(get_timestamp() - Duration::seconds(60))),
+            None,
+        )?;
+        let ignored = helper::create_stopped_worklog(
+            conn,
+            subtask.id,
+            ts("2020-05-10 09:00:00"),
+            ts("2020-05-10 10:00:00"),
+            3600,
+        )?;
+        tracker.set_worklog_ignored(ignored.id, true)?;
+
+        let rows = tracker.list_tasks_with_spent(None, Some("all"), &[])?;
+        let spents: Vec<(&str, i32, i32)> = rows
+            .iter()
+            .map(|row| (row.task.taskname.as_ref(), row.spent, row.current_spent))
+            .collect();
+        assert_eq!(spents.len(), 3);
+        assert_eq!(spents[0].0, "task1");
+        assert!((60..70).contains(&spents[0].2));
+        assert_eq!(spents[0].1, 5400 + spents[0].2);
+        assert_eq!(spents[1], ("task1::sub", 0, 0));
+        assert_eq!(spents[2], ("task2", 0, 0));
+
+        let rows = tracker.list_tasks_with_spent(Some("task1"), None, &[])?;
+        assert_eq!(rows.len(), 2);
+        assert!(tracker
+            .list_tasks_with_spent(None, Some("done"), &[])?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn tags() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        let tags =
+            |names: &[&str]| -> Vec<String> { names.iter().map(|&n| n.to_owned()).collect() };
+        tracker.add_tags("task1", &tags(&["a", "b"]))?;
+        tracker.add_tags("task2", &tags(&["a", "a"]))?;
+
+        let names = |list: Vec<models::Task>| -> Vec<String> {
+            list.into_iter().map(|t| t.taskname).collect()
+        };
+        assert_eq!(
+            names(tracker.list_tasks(None, None, &tags(&["a"]))?).len(),
+            2
+        );
+        assert_eq!(
+            names(tracker.list_tasks(None, None, &tags(&["a", "b"]))?),
+            vec!["task1"]
+        );
+        assert!(matches!(
+            tracker.list_tasks(None, None, &tags(&["c"])),
+            Err(Error::TagNotFound { .. })
+        ));
+
+        let listed: Vec<(String, i32)> = tracker
+            .list_tags()?
+            .into_iter()
+            .map(|row| (row.tag.name, row.tasks))
+            .collect();
+        assert_eq!(listed, vec![("a".to_owned(), 2), ("b".to_owned(), 1)]);
+
+        // unused tags are deleted
+        tracker.remove_tags("task1", &tags(&["b", "c"]))?;
+        tracker.delete_task("task2")?;
+        let listed: Vec<String> = tracker
+            .list_tags()?
+            .into_iter()
+            .map(|row| row.tag.name)
+            .collect();
+        assert_eq!(listed, vec!["a"]);
+        assert!(helper::get_tag_id(conn, "b").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn worklog_notes() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        let names = vec!["task1".to_owned(), "task2".to_owned()];
+
+        tracker.start_worklogs(&names[..1], None, Some("review"))?;
+        tracker.start_worklogs(&names[1..], None, None)?;
+        tracker.stop_worklogs(&names, None, Some("done"))?;
+        let notes: Vec<Option<String>> = tracker
+            .list_worklogs(None, false, None)?
+            .into_iter()
+            .map(|(w, _)| w.notes)
+            .collect();
+        assert_eq!(
+            notes,
+            vec![Some("review; done".to_owned()), Some("done".to_owned())]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pause_resume() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        self::create_task(conn, "task1", None, None, None)?;
+        let names = vec!["task1".to_owned()];
+        let now = get_timestamp();
+        let ago = |minutes: i64| Some(now - Duration::minutes(minutes));
+
+        tracker.start_worklogs(&names, ago(120), None)?;
+        tracker.pause_worklogs(&names, ago(90))?;
+        assert!(matches!(
+            tracker.resume_worklogs(&names, ago(100)),
+            Err(Error::InvalidTimeRange)
+        ));
+        tracker.resume_worklogs(&names, ago(60))?;
+        tracker.pause_worklogs(&names, ago(30))?;
+        let events = tracker.pause_worklogs(&names, None)?;
+        assert!(matches!(events[0], Err(Error::TaskIsPaused { .. })));
+
+        let running = tracker.get_running_tasks(None, &[])?;
+        assert!(running[0].paused_at.is_some());
+        assert!((7200..7210).contains(&running[0].current_wall));
+        assert_eq!(running[0].current_spent, 3600);
+        assert_eq!(tracker.get_total_spent("task1")?, 3600);
+        let value = serde_json::to_value(&running[0])?;
+        assert_eq!(value["started"], utc_to_iso8601(&running[0].started));
+        assert_eq!(value["paused_at"], utc_to_iso8601(&ago(30).unwrap()));
+        assert_eq!(value["current_spent"], 3600);
+
+        // stopping ends the open break
+        tracker.stop_worklogs(&names, ago(10), None)?;
+        let (worklog_obj, _) = tracker.list_worklogs(None, false, None)?.remove(0);
+        assert_eq!(worklog_obj.duration, 6600 - 3000);
+        let events = tracker.resume_worklogs(&names, None)?;
+        assert!(matches!(events[0], Err(Error::TaskIsNotRunning { .. })));
+
+        let today = Local::today().naive_local();
+        let entries = tracker.get_report(today.pred(), today, None, &[])?;
+        assert_eq!(entries.iter().map(|e| e.spent).sum::<i32>(), 3600);
+        assert_eq!(entries.iter().map(|e| e.breaks).sum::<i32>(), 3000);
+
+        // breaks outside the edited period are not counted
+        tracker.update_worklog(worklog_obj.id, ago(50), None)?;
+        assert_eq!(tracker.get_total_spent("task1")?, 2400 - 1200);
+        tracker.delete_worklog(worklog_obj.id)?;
+        assert_eq!(schema::pause::table.count().get_result::<i64>(conn)?, 0);
+
+        // setting the end of a paused session ends its break as well
+        tracker.start_worklogs(&names, ago(120), None)?;
+        tracker.pause_worklogs(&names, ago(60))?;
+        let (worklog_obj, _) = tracker.list_worklogs(None, false, None)?.remove(0);
+        assert!(matches!(
+            tracker.update_worklog(worklog_obj.id, None, ago(90)),
+            Err(Error::InvalidTimeRange)
+        ));
+        let worklog_obj = tracker.update_worklog(worklog_obj.id, None, ago(30))?;
+        assert_eq!(worklog_obj.duration, 3600);
+        let pause_obj = models::Pause::belonging_to(&worklog_obj).first::<models::Pause>(conn)?;
+        assert_eq!(pause_obj.stopped, worklog_obj.stopped);
+        let entries = tracker.get_report(today.pred(), today, None, &[])?;
+        assert_eq!(entries.iter().map(|e| e.breaks).sum::<i32>(), 1800);
+        Ok(())
+    }
+
+    #[test]
+    fn rename_task() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        for name in ["proj", "proj::a", "proj::a::x", "proj_b", "other"].iter() {
+            self::create_task(conn, name, None, None, None)?;
+        }
+        let taskid = helper::get_task_id(conn, "proj::a")?;
+        helper::create_worklog(conn, taskid, None, None)?;
+
+        let renamed = tracker.rename_task("other", "misc", false)?;
+        assert_eq!(renamed, vec![("other".to_owned(), "misc".to_owned())]);
+        assert!(matches!(
+            tracker.rename_task("misc", "proj", false),
+            Err(Error::TaskAlreadyExists { taskname }) if taskname == "proj"
+        ));
+
+        // moving a subtree into itself reuses names of renamed tasks
+        let renamed = tracker.rename_task("proj", "proj::a", true)?;
+        let names: Vec<&str> = renamed.iter().map(|(_, new)| new.as_str()).collect();
+        assert_eq!(names, vec!["proj::a", "proj::a::a", "proj::a::a::x"]);
+        assert_eq!(helper::get_task_id(conn, "proj::a::a")?, taskid);
+        assert!(helper::check_task_is_running(
+            conn,
+            &helper::get_task(conn, "proj::a::a")?
+        )?);
+        assert!(tracker.check_task_exists("proj_b")?);
+
+        // a collision in the subtree leaves every name unchanged
+        self::create_task(conn, "new::a::x", None, None, None)?;
+        assert!(matches!(
+            tracker.rename_task("proj::a::a", "new::a", true),
+            Err(Error::TaskAlreadyExists { taskname }) if taskname == "new::a::x"
+        ));
+        assert!(tracker.check_task_exists("proj::a::a")?);
+        assert!(!tracker.check_task_exists("new::a")?);
+        Ok(())
+    }
+
+    #[test]
+    fn merge_tasks() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        self::create_task(conn, "bugfix", Some("first"), Some(2), None)?;
+        self::create_task(
+            conn,
+            "bugfixes",
+            Some("second"),
+            Some(3),
+            Some("2020-06-30"),
+        )?;
+        tracker.add_tags("bugfix", &["api".to_owned()])?;
+        tracker.add_tags("bugfixes", &["api".to_owned(), "urgent".to_owned()])?;
+        let sourceid = helper::get_task_id(conn, "bugfixes")?;
+        let targetid = helper::get_task_id(conn, "bugfix")?;
+        helper::create_worklog(conn, sourceid, Some(ts("2020-06-01 09:00:00")), None)?;
+        helper::create_worklog(conn, targetid, Some(ts("2020-06-01 12:00:00")), None)?;
+
+        assert!(matches!(
+            tracker.merge_tasks(
+                "bugfixes",
+                "bugfix",
+                MergeNotes::Append,
+                MergeAllocation::Sum,
+                false
+            ),
+            Err(Error::TasksAreRunning { .. })
+        ));
+        assert!(tracker.check_task_exists("bugfixes")?);
+        assert!(matches!(
+            tracker.merge_tasks(
+                "bugfix",
+                "bugfix",
+                MergeNotes::Keep,
+                MergeAllocation::Keep,
+                true
+            ),
+            Err(Error::MergeIntoItself { .. })
+        ));
+
+        let merged = tracker.merge_tasks(
+            "bugfixes",
+            "bugfix",
+            MergeNotes::Append,
+            MergeAllocation::Sum,
+            true,
+        )?;
+        assert_eq!(merged.stopped.unwrap().taskname, "bugfixes");
+        assert_eq!(merged.worklogs, 1);
+        // the stopped source session ends when the target one started
+        assert_eq!(merged.adjusted, 1);
+        assert_eq!(merged.task.notes.as_deref(), Some("first\nsecond"));
+        assert_eq!(merged.task.allocated, 5);
+        assert_eq!(merged.task.duedate.as_deref(), Some("2020-06-30"));
+        assert!(!tracker.check_task_exists("bugfixes")?);
+        let worklogs = tracker.list_worklogs(None, false, None)?;
+        assert_eq!(worklogs.len(), 2);
+        assert!(worklogs.iter().all(|(w, _)| w.task_id == targetid));
+        let tags = tracker.get_task_tags()?;
+        assert_eq!(tags[&targetid], vec!["api", "urgent"]);
+        assert!(worklogs
+            .iter()
+            .any(|(w, _)| w.stopped == Some(ts("2020-06-01 12:00:00"))));
+
+        // cut short before the target's session, ignored when starting inside it
+        self::create_task(conn, "hotfix", None, None, None)?;
+        let hotfixid = helper::get_task_id(conn, "hotfix")?;
+        helper::create_stopped_worklog(
+            conn,
+            hotfixid,
+            ts("2020-06-01 08:00:00"),
+            ts("2020-06-01 09:30:00"),
+            5400,
+        )?;
+        helper::create_stopped_worklog(
+            conn,
+            hotfixid,
+            ts("2020-06-01 10:00:00"),
+            ts("2020-06-01 11:00:00"),
+            3600,
+        )?;
+        let merged = tracker.merge_tasks(
+            "hotfix",
+            "bugfix",
+            MergeNotes::Keep,
+            MergeAllocation::Keep,
+            false,
+        )?;
+        assert_eq!((merged.worklogs, merged.adjusted), (2, 2));
+        let worklogs = tracker.list_worklogs(None, true, None)?;
+        let early = worklogs
+            .iter()
+            .find(|(w, _)| w.started == ts("2020-06-01 08:00:00"))
+            .unwrap();
+        assert_eq!(early.0.stopped, Some(ts("2020-06-01 09:00:00")));
+        assert_eq!(early.0.duration, 3600);
+        let inside = worklogs
+            .iter()
+            .find(|(w, _)| w.started == ts("2020-06-01 10:00:00"))
+            .unwrap();
+        assert!(inside.0.ignored);
+        Ok(())
+    }
+
+    #[test]
+    fn trash() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        tracker.add_tags("task1", &["a".to_owned()])?;
+        let taskid = helper::get_task_id(conn, "task1")?;
+        helper::create_worklog(conn, taskid, None, None)?;
+
+        let stopped = tracker.delete_task("task1")?;
+        assert_eq!(stopped.unwrap().taskname, "task1");
+        let names: Vec<String> = tracker
+            .list_tasks(None, None, &[])?
+            .into_iter()
+            .map(|t| t.taskname)
+            .collect();
+        assert_eq!(names, vec!["task2"]);
+        assert!(tracker.list_tags()?.is_empty());
+        assert!(tracker.list_worklogs(None, true, None)?.is_empty());
+        assert!(matches!(
+            tracker.start_worklogs(&["task1".to_owned()], None, None),
+            Err(Error::TaskIsTrashed { .. })
+        ));
+        assert!(matches!(
+            tracker.create_task("task1", None, None, None),
+            Err(Error::TaskIsTrashed { .. })
+        ));
+        let trashed = tracker.list_trash()?;
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].task.taskname, "task1");
+
+        tracker.restore_task("task1")?;
+        assert!(matches!(
+            tracker.restore_task("task1"),
+            Err(Error::TaskIsNotTrashed { .. })
+        ));
+        assert_eq!(tracker.list_worklogs(None, false, None)?.len(), 1);
+
+        tracker.delete_task("task1")?;
+        let hour_ago = get_timestamp() - Duration::hours(1);
+        assert!(tracker.purge_trash(Some(hour_ago))?.is_empty());
+        assert_eq!(tracker.purge_trash(None)?, vec!["task1"]);
+        assert!(helper::find_task(conn, "task1")?.is_none());
+        assert!(helper::get_tag_id(conn, "a").is_err());
+        assert_eq!(schema::worklog::table.count().get_result::<i64>(conn)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn undo() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        let names = vec!["task1".to_owned(), "task2".to_owned()];
+        tracker.create_task("task1", Some("notes"), None, None)?;
+        tracker.create_task("task2", None, None, None)?;
+        tracker.add_tags("task2", &["a".to_owned()])?;
+        tracker.start_worklogs(&names, None, None)?;
+        tracker.record("stopall", || -> Result<(), Error> {
+            for name in names.iter() {
+                tracker.stop_worklogs(&[name.to_owned()], None, None)?;
+            }
+            Ok(())
+        })?;
+        // skipped starts change nothing and are not recorded
+        tracker.stop_worklogs(&names, None, None)?;
+        tracker.merge_tasks(
+            "task2",
+            "task1",
+            MergeNotes::Keep,
+            MergeAllocation::Keep,
+            false,
+        )?;
+        tracker.rename_task("task1", "main", false)?;
+
+        let history = tracker.history(None)?;
+        let commands: Vec<&str> = history.iter().map(|op| op.command.as_str()).collect();
+        assert_eq!(
+            commands,
+            vec![
+                "rename task1 main",
+                "merge task2 task1",
+                "stopall",
+                "start task1 task2",
+                "tag task2",
+                "create task2",
+                "create task1"
+            ]
+        );
+        let stopped = &history[2].changes;
+        assert_eq!(stopped.len(), 2);
+        assert_eq!(stopped[0].kind, ChangeKind::Updated);
+        assert!(stopped[0].columns.contains(&"stopped".to_owned()));
+
+        let undone = tracker.undo(2)?;
+        assert_eq!(undone[0].command, "rename task1 main");
+        assert!(!tracker.check_task_exists("main")?);
+        let task2 = helper::get_task(conn, "task2")?;
+        let worklogs = tracker.list_worklogs(None, false, None)?;
+        assert!(worklogs
+            .iter()
+            .any(|(w, t)| t.id == task2.id && w.task_id == task2.id));
+        assert_eq!(tracker.get_task_tags()?[&task2.id], vec!["a"]);
+        assert_eq!(tracker.history(None)?.len(), 5);
+
+        tracker.undo(1)?;
+        assert_eq!(tracker.get_running_tasks(None, &[])?.len(), 2);
+        tracker.undo(10)?;
+        assert!(tracker.list_tasks(None, None, &[])?.is_empty());
+        assert!(helper::get_tag_id(conn, "a").is_err());
+        assert!(tracker.undo(1)?.is_empty());
+
+        // an operation left open by an interrupted command is closed by the next one
+        helper::open_undo_op(conn, "delete task1")?;
+        self::create_task(conn, "task1", None, None, None)?;
+        tracker.create_task("task2", None, None, None)?;
+        let history = tracker.history(None)?;
+        let commands: Vec<&str> = history.iter().map(|op| op.command.as_str()).collect();
+        assert_eq!(commands, vec!["create task2", "delete task1"]);
+        assert_eq!(history[0].changes.len(), 1);
+        assert_eq!(history[1].changes.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn recurring() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        self::create_task(
+            conn,
+            "standup",
+            Some("daily sync"),
+            Some(900),
+            Some("2020-06-05"),
+        )?;
+        self::create_task(conn, "task2", None, None, None)?;
+        tracker.add_tags("standup", &["team".to_owned()])?;
+        let rule: Recurrence = "weekdays".parse()?;
+        tracker.set_recurrence("standup", Some(&rule))?;
+        let recurring = tracker.list_recurring()?;
+        assert_eq!(recurring.len(), 1);
+        assert_eq!(recurring[0].recur.as_deref(), Some("weekdays"));
+
+        // one-off tasks are just completed
+        let task2 = tracker.update_tasks("task2", None, None, None, Some(true))?;
+        assert_eq!(task2.taskname, "task2");
+
+        let standup = helper::get_task(conn, "standup")?;
+        let completed = tracker.update_tasks("standup", None, None, None, Some(true))?;
+        assert_eq!(completed.taskname, "standup@2020-06-05");
+        assert!(completed.done);
+        assert!(completed.recur.is_none());
+        let renewed = helper::get_task(conn, "standup")?;
+        assert!(!renewed.done);
+        assert_eq!(renewed.notes.as_deref(), Some("daily sync"));
+        assert_eq!(renewed.allocated, 900);
+        assert_eq!(renewed.recur.as_deref(), Some("weekdays"));
+        let today = Local::today().naive_local();
+        let due = Recurrence::Weekdays.next_due(NaiveDate::from_ymd(2020, 6, 5), today);
+        assert_eq!(renewed.duedate, Some(due.to_string()));
+        assert_eq!(tracker.get_task_tags()?[&renewed.id], vec!["team"]);
+
+        // undo takes the completed task back
+        tracker.undo(1)?;
+        assert!(helper::find_task(conn, "standup@2020-06-05")?.is_none());
+        let restored = helper::get_task(conn, "standup")?;
+        assert_eq!(restored.id, standup.id);
+        assert!(!restored.done);
+        assert_eq!(restored.recur.as_deref(), Some("weekdays"));
+
+        // instance names stay unique when completed twice on a due date
+        tracker.update_tasks("standup", None, None, None, Some(true))?;
+        tracker.update_tasks("standup", None, None, Some("2020-06-05"), None)?;
+        let renewed = helper::get_task(conn, "standup")?;
+        let next = helper::flag_complete(conn, &renewed)?.unwrap();
+        assert_eq!(next.taskname, "standup");
+        assert!(helper::get_task(conn, "standup@2020-06-05-2")?.done);
+
+        tracker.set_recurrence("standup", None)?;
+        assert!(tracker.list_recurring()?.is_empty());
+
+        // bare monthly rules keep the day of the due date
+        self::create_task(conn, "invoice", None, None, Some("2020-01-31"))?;
+        let rule: Recurrence = "monthly".parse()?;
+        let invoice = tracker.set_recurrence("invoice", Some(&rule))?;
+        assert_eq!(invoice.recur.as_deref(), Some("monthly:31"));
+        assert!(matches!(
+            "monthly:40".parse::<Recurrence>(),
+            Err(Error::InvalidRecurrence { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn switch_worklog() -> Result<(), Error> {
+        let mut tracker = Tracker::in_memory()?;
+        tracker.set_single_active(true);
+        let conn = &tracker.conn;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        let now = get_timestamp();
+        let ago = |minutes: i64| Some(now - Duration::minutes(minutes));
+
+        let switched = tracker.switch_worklog("task1", ago(60), None)?;
+        assert!(switched.stopped.is_empty());
+        assert_eq!(switched.started.unwrap().taskname, "task1");
+        let events = tracker.start_worklogs(&["task2".to_owned()], None, None)?;
+        assert!(matches!(
+            &events[0],
+            Err(Error::AnotherTaskIsRunning { taskname }) if taskname == "task1"
+        ));
+
+        let switched = tracker.switch_worklog("task2", ago(30), Some("next"))?;
+        assert_eq!(switched.stopped[0].taskname, "task1");
+        assert_eq!(switched.started.unwrap().timestamp, ago(30).unwrap());
+        let worklogs = tracker.list_worklogs(None, false, None)?;
+        assert_eq!(worklogs[0].0.stopped, Some(worklogs[1].0.started));
+        assert_eq!(worklogs[1].0.notes.as_deref(), Some("next"));
+        assert!(tracker
+            .switch_worklog("task2", None, None)?
+            .started
+            .is_none());
+
+        // stopping task2 before it started rolls back the whole switch
+        assert!(matches!(
+            tracker.switch_worklog("task1", ago(40), None),
+            Err(Error::InvalidTimeRange)
+        ));
+        let running = tracker.get_running_tasks(None, &[])?;
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].name, "task2");
+        Ok(())
+    }
+
+    #[test]
+    fn list_running() -> Result<(), Error> {
+        let (_tempdir, dbpath) = self::setup()?;
+        let dbpath = dbpath.to_str().unwrap();
+        let tracker = Tracker::open(dbpath)?;
+        let conn = &tracker.conn;
+        self::create_task(conn, "task1", None, None, None)?;
+        self::create_task(conn, "task2", None, None, None)?;
+        let now = get_timestamp();
+        let ago = |minutes: i64| Some(now - Duration::minutes(minutes));
+        tracker.log_worklog("task1", now - Duration::hours(5), now - Duration::hours(4))?;
+        tracker.start_worklogs(&["task1".to_owned()], ago(90), None)?;
+        tracker.start_worklogs(&["task2".to_owned()], ago(60), None)?;
+        tracker.pause_worklogs(&["task1".to_owned()], ago(30))?;
+
+        let reader = Tracker::open_read_only(dbpath)?.unwrap();
+        let running = reader.list_running()?;
+        assert_eq!(running.len(), 2);
+        assert_eq!(running[0].name, "task2");
+        assert_eq!(running[1].name, "task1");
+        assert!(running[1].paused_at.is_some());
+        assert!((3600..3610).contains(&running[1].current_spent));
+        assert!((5400..5410).contains(&running[1].current_wall));
+        assert_eq!(running[1].spent, 3600 + running[1].current_spent);
+        assert!(reader.create_task("task3", None, None, None).is_err());
+        assert!(Tracker::open_read_only("/nonexistent/ttrackr.db")?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn task_tree() {
+        let taskobj = |taskid: i32, name: &str, allocated: i32| models::Task {
+            id: taskid,
+            created: ts("2020-05-10 00:00:00"),
+            taskname: name.to_owned(),
+            notes: None,
+            allocated,
+            duedate: None,
+            done: false,
+            deleted_at: None,
+            recur: None,
+        };
+        let rows = vec![
+            (taskobj(1, "b", 0), 10),
+            (taskobj(2, "a::x::y", 60), 20),
+            (taskobj(3, "a", 30), 5),
+            (taskobj(4, "a::z", 0), 7),
+            (taskobj(5, "a-b", 0), 1),
+        ];
+        let tree = build_task_tree(rows);
+        let names: Vec<(&str, usize)> = tree.iter().map(|n| (n.name.as_ref(), n.depth)).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("a", 0),
+                ("a::x", 1),
+                ("a::x::y", 2),
+                ("a::z", 1),
+                ("a-b", 0),
+                ("b", 0)
+            ]
+        );
+        assert_eq!((tree[0].own_spent, tree[0].spent), (5, 32));
+        assert_eq!((tree[0].own_allocated, tree[0].allocated), (30, 90));
+        assert!(tree[1].task.is_none());
+        assert_eq!((tree[1].spent, tree[1].allocated), (20, 60));
+        assert_eq!(tree[2].label(), "y");
+    }
+
+    #[test]
+    fn skipped_start_and_stop() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+
+        tracker.create_task("task1", None, None, None)?;
+        assert!(matches!(
+            tracker.create_task("task1", None, None, None),
+            Err(Error::TaskAlreadyExists { .. })
+        ));
+        assert!(matches!(
+            tracker.start_worklogs(&["nope".to_owned()], None, None),
+            Err(Error::TaskNotFound { .. })
+        ));
+
+        let names = vec!["task1".to_owned()];
+        let events = tracker.start_worklogs(&names, None, None)?;
+        assert_eq!(events[0].as_ref().unwrap().taskname, "task1");
+        let events = tracker.start_worklogs(&names, None, None)?;
+        assert!(matches!(events[0], Err(Error::TaskIsAlreadyRunning { .. })));
+
+        tracker.stop_worklogs(&names, None, None)?;
+        let events = tracker.stop_worklogs(&names, None, None)?;
+        assert!(matches!(events[0], Err(Error::TaskIsNotRunning { .. })));
+
+        let task1 = helper::get_task(conn, "task1")?;
+        helper::flag_complete(conn, &task1)?;
+        let events = tracker.start_worklogs(&names, None, None)?;
+        assert!(matches!(events[0], Err(Error::TaskIsCompleted { .. })));
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_batch_changes_nothing() -> Result<(), Error> {
+        let tracker = Tracker::in_memory()?;
+        let conn = &tracker.conn;
+        for name in &["task1", "task2", "task3"] {
+            self::create_task(conn, name, None, None, None)?;
+        }
+        let now = get_timestamp();
+        let ago = |minutes: i64| Some(now - Duration::minutes(minutes));
+        tracker.start_worklogs(&["task1".to_owned()], ago(120), None)?;
+        tracker.start_worklogs(&["task2".to_owned()], ago(60), None)?;
+
+        // task2 cannot stop before it started, task1 keeps running
+        let names = vec!["task1".to_owned(), "task2".to_owned()];
+        assert!(matches!(
+            tracker.stop_worklogs(&names, ago(90), None),
+            Err(Error::InvalidTimeRange)
+        ));
+        assert_eq!(helper::get_running_task_ids(conn, &[])?.len(), 2);
+        let stops = vec![
+            ("task1".to_owned(), ago(100)),
+            ("task2".to_owned(), ago(90)),
+        ];
+        assert!(matches!(
+            tracker.stop_worklogs_at(&stops, None),
+            Err(Error::InvalidTimeRange)
+        ));
+        assert_eq!(helper::get_running_task_ids(conn, &[])?.len(), 2);
+
+        // task2 already has a session then, task3 is not started
+        let names = vec!["task3".to_owned(), "task2".to_owned()];
+        tracker.stop_worklogs(&names[1..], ago(30), None)?;
+        assert!(matches!(
+            tracker.start_worklogs(&names, ago(45), None),
+            Err(Error::WorklogOverlaps { .. })
+        ));
+        let task3 = helper::get_task(conn, "task3")?;
+        assert!(!helper::check_task_is_running(conn, &task3)?);
+
+        // task3 cannot be paused or resumed before, task1 keeps its state
+        let names = vec!["task1".to_owned(), "task3".to_owned()];
+        tracker.start_worklogs(&names[1..], ago(20), None)?;
+        assert!(matches!(
+            tracker.pause_worklogs(&names, ago(25)),
+            Err(Error::InvalidTimeRange)
+        ));
+        let paused = |tracker: &Tracker| -> Result<usize, Error> {
+            let running = tracker.get_running_tasks(None, &[])?;
+            Ok(running.iter().filter(|t| t.paused_at.is_some()).count())
+        };
+        assert_eq!(paused(&tracker)?, 0);
+        tracker.pause_worklogs(&names[..1], ago(15))?;
+        tracker.pause_worklogs(&names[1..], ago(5))?;
+        assert!(matches!(
+            tracker.resume_worklogs(&names, ago(10)),
+            Err(Error::InvalidTimeRange)
+        ));
+        assert_eq!(paused(&tracker)?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn reopen_database() -> Result<(), Error> {
         let (_tempdir, dbpath) = setup()?;
         let conn_str = dbpath.to_string_lossy().to_string();
-        let conn = establish_connection(&conn_str)?;
+        let tracker = Tracker::open(&conn_str)?;
+        tracker.create_task("task1", None, None, None)?;
+        drop(tracker);
+
+        let tracker = Tracker::open(&conn_str)?;
+        assert_eq!(tracker.list_tasks(None, None, &[])?.len(), 1);
+        assert!(Tracker::in_memory()?
+            .list_tasks(None, None, &[])?
+            .is_empty());
+        Ok(())
+    }
 
-        let mut conf = Config::new();
-        conf.autodone = true;
-        conf.database.insert("path".to_owned(), conn_str);
+    #[test]
+    fn complete_task_on_stop() -> Result<(), Error> {
+        let mut tracker = Tracker::in_memory()?;
+        tracker.set_autodone(true);
+        let conn = &tracker.conn;
 
-        self::create_task(&conn, "task1", None, None, None)?;
-        let mut task1 = helper::get_task(&conn, "task1")?;
+        self::create_task(conn, "task1", None, None, None)?;
+        let mut task1 = helper::get_task(conn, "task1")?;
         let mut updateobj = task1.create_changeset();
-        updateobj.allocated = 1;
-        match diesel::update(&updateobj).set(&updateobj).execute(&conn) {
+        updateobj.allocated = Some(1);
+        match diesel::update(&updateobj).set(&updateobj).execute(conn) {
             Ok(_) => (),
             Err(err) => return Err(err.into()),
         };
 
-        helper::create_worklog(&conn, task1.id)?;
+        helper::create_worklog(conn, task1.id, None, None)?;
         pause(1100);
-        stop_worklogs(&conf, &vec!["task1".to_owned()])?;
+        tracker.stop_worklogs(&vec!["task1".to_owned()], None, None)?;
 
-        task1 = helper::get_task(&conn, "task1")?;
+        task1 = helper::get_task(conn, "task1")?;
 
         assert_eq!(task1.done, true);
 
@@ -1,12 +1,14 @@
 table! {
     task (id) {
         id -> Integer,
-        created -> Timestamp,
+        created -> BigInt,
         taskname -> Text,
         notes -> Nullable<Text>,
         allocated -> Integer,
         duedate -> Nullable<Timestamp>,
         done -> Bool,
+        deleted_at -> Nullable<BigInt>,
+        recur -> Nullable<Text>,
     }
 }
 
@@ -14,13 +16,60 @@ table! {
     worklog (id) {
         id -> Integer,
         task_id -> Integer,
-        started -> Timestamp,
-        stopped -> Nullable<Timestamp>,
+        started -> BigInt,
+        stopped -> Nullable<BigInt>,
         duration -> Integer,
         ignored -> Bool,
+        notes -> Nullable<Text>,
+    }
+}
+
+table! {
+    tag (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+table! {
+    task_tag (task_id, tag_id) {
+        task_id -> Integer,
+        tag_id -> Integer,
+    }
+}
+
+table! {
+    pause (id) {
+        id -> Integer,
+        worklog_id -> Integer,
+        started -> BigInt,
+        stopped -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    undo_op (id) {
+        id -> Integer,
+        created -> BigInt,
+        command -> Text,
+        open -> Bool,
+    }
+}
+
+table! {
+    undo_log (id) {
+        id -> Integer,
+        op_id -> Integer,
+        tablename -> Text,
+        before -> Nullable<Text>,
+        after -> Nullable<Text>,
     }
 }
 
 joinable!(worklog -> task (task_id));
+joinable!(task_tag -> task (task_id));
+joinable!(task_tag -> tag (tag_id));
+joinable!(pause -> worklog (worklog_id));
+joinable!(undo_log -> undo_op (op_id));
 
-allow_tables_to_appear_in_same_query!(task, worklog,);
+allow_tables_to_appear_in_same_query!(task, worklog, tag, task_tag, pause, undo_op, undo_log,);
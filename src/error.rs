@@ -0,0 +1,186 @@
+// errors returned by the library
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug)]
+pub enum Error {
+    TaskNotFound {
+        taskname: String,
+    },
+    WorklogNotFound {
+        id: i32,
+    },
+    TagNotFound {
+        tag: String,
+    },
+    TaskIsAlreadyRunning {
+        taskname: String,
+    },
+    TaskIsNotRunning {
+        taskname: String,
+    },
+    /// Another task is running while only a single active task is allowed.
+    AnotherTaskIsRunning {
+        taskname: String,
+    },
+    /// Tasks to merge are both running.
+    TasksAreRunning {
+        source: String,
+        target: String,
+    },
+    MergeIntoItself {
+        taskname: String,
+    },
+    TaskIsPaused {
+        taskname: String,
+    },
+    TaskIsNotPaused {
+        taskname: String,
+    },
+    /// Completed tasks cannot be started.
+    TaskIsCompleted {
+        taskname: String,
+    },
+    TaskAlreadyExists {
+        taskname: String,
+    },
+    TaskIsTrashed {
+        taskname: String,
+    },
+    TaskIsNotTrashed {
+        taskname: String,
+    },
+    WorklogOverlaps {
+        taskname: String,
+    },
+    /// A timestamp that is not in `%Y-%m-%d %H:%M:%S` format or does not exist
+    /// in the local timezone.
+    InvalidTimestamp {
+        value: String,
+    },
+    /// End time earlier than start time.
+    InvalidTimeRange,
+    /// A recurrence rule other than `daily`, `weekdays`, `weekly[:mon,...]` or
+    /// `monthly[:day]`.
+    InvalidRecurrence {
+        value: String,
+    },
+    UnsupportedArchive {
+        version: u32,
+    },
+    /// Malformed archive or export of another time tracker.
+    InvalidData(String),
+    Io(std::io::Error),
+    Connection(diesel::ConnectionError),
+    Migration(diesel_migrations::RunMigrationsError),
+    Database(diesel::result::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::TaskNotFound { taskname } => write!(f, "Task {} not found", taskname),
+            Error::WorklogNotFound { id } => write!(f, "Worklog #{} not found", id),
+            Error::TagNotFound { tag } => write!(f, "Tag {} not found", tag),
+            Error::TaskIsAlreadyRunning { taskname } => {
+                write!(f, "{} is already running", taskname)
+            }
+            Error::TaskIsNotRunning { taskname } => write!(f, "{} is not running", taskname),
+            Error::AnotherTaskIsRunning { taskname } => {
+                write!(
+                    f,
+                    "{} is running, only one task can run at a time",
+                    taskname
+                )
+            }
+            Error::TasksAreRunning { source, target } => {
+                write!(f, "{} and {} are both running", source, target)
+            }
+            Error::MergeIntoItself { taskname } => {
+                write!(f, "Cannot merge {} into itself", taskname)
+            }
+            Error::TaskIsPaused { taskname } => write!(f, "{} is paused", taskname),
+            Error::TaskIsNotPaused { taskname } => write!(f, "{} is not paused", taskname),
+            Error::TaskIsCompleted { taskname } => write!(f, "{} is completed", taskname),
+            Error::TaskAlreadyExists { taskname } => write!(f, "{} already exists", taskname),
+            Error::TaskIsTrashed { taskname } => write!(f, "{} is in the trash", taskname),
+            Error::TaskIsNotTrashed { taskname } => {
+                write!(f, "{} is not in the trash", taskname)
+            }
+            Error::WorklogOverlaps { taskname } => {
+                write!(f, "{} already has a worklog in that period", taskname)
+            }
+            Error::InvalidTimestamp { value } => write!(f, "Invalid timestamp: {}", value),
+            Error::InvalidTimeRange => write!(f, "End time must be later than start time"),
+            Error::InvalidRecurrence { value } => {
+                write!(f, "Invalid recurrence rule: {}", value)
+            }
+            Error::UnsupportedArchive { version } => {
+                write!(f, "Unsupported archive version {}", version)
+            }
+            Error::InvalidData(msg) => write!(f, "Invalid data: {}", msg),
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Connection(err) => write!(f, "Cannot open database: {}", err),
+            Error::Migration(err) => write!(f, "Cannot migrate database: {}", err),
+            Error::Database(err) => write!(f, "Database error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Connection(err) => Some(err),
+            Error::Migration(err) => Some(err),
+            Error::Database(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<diesel::ConnectionError> for Error {
+    fn from(err: diesel::ConnectionError) -> Self {
+        Error::Connection(err)
+    }
+}
+
+impl From<diesel_migrations::RunMigrationsError> for Error {
+    fn from(err: diesel_migrations::RunMigrationsError) -> Self {
+        Error::Migration(err)
+    }
+}
+
+impl From<diesel::result::Error> for Error {
+    fn from(err: diesel::result::Error) -> Self {
+        Error::Database(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            Error::Io(err.into())
+        } else {
+            Error::InvalidData(err.to_string())
+        }
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        if err.is_io_error() {
+            match err.into_kind() {
+                csv::ErrorKind::Io(err) => Error::Io(err),
+                _ => unreachable!(),
+            }
+        } else {
+            Error::InvalidData(err.to_string())
+        }
+    }
+}
@@ -8,6 +8,13 @@ extern crate diesel;
 extern crate diesel_migrations;
 
 pub mod cli;
+pub mod completions;
 pub mod config;
 pub mod db;
+pub mod error;
+pub mod output;
+pub mod recurrence;
 pub mod utils;
+
+pub use db::ops::Tracker;
+pub use error::Error;
@@ -0,0 +1,142 @@
+// structured output for listing commands
+use crate::utils::BoxError;
+use serde::Serialize;
+use std::io;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_ref() {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err("no match"),
+        }
+    }
+}
+
+/// Write `records` to stdout as JSON array, CSV or TSV.
+///
+/// Records must be flat structs for CSV and TSV, the header of an empty listing
+/// is taken from their `Default` value. Nothing is written for `Table`,
+/// rendering tables is left to the caller.
+pub fn print_records<T: Serialize + Default>(
+    format: OutputFormat,
+    records: &[T],
+) -> Result<(), BoxError> {
+    let stdout = io::stdout();
+    write_records(format, records, &mut stdout.lock())
+}
+
+pub fn write_records<T: Serialize + Default>(
+    format: OutputFormat,
+    records: &[T],
+    out: &mut impl io::Write,
+) -> Result<(), BoxError> {
+    match format {
+        OutputFormat::Table => (),
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, records)?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv | OutputFormat::Tsv => {
+            let delimiter = if format == OutputFormat::Csv {
+                b','
+            } else {
+                b'\t'
+            };
+            if records.is_empty() {
+                // headers are only written along with the first record
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .from_writer(Vec::new());
+                writer.serialize(T::default())?;
+                let data = writer.into_inner().map_err(|err| err.to_string())?;
+                let end = data
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .map_or(data.len(), |pos| pos + 1);
+                out.write_all(&data[..end])?;
+                return Ok(());
+            }
+            let mut writer = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .from_writer(out);
+            for record in records.iter() {
+                writer.serialize(record)?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::Serialize;
+
+    #[derive(Serialize, Default)]
+    struct Record {
+        name: &'static str,
+        spent: i32,
+        note: Option<&'static str>,
+    }
+
+    fn render(format: OutputFormat) -> Result<String, BoxError> {
+        let records = vec![
+            Record {
+                name: "task1",
+                spent: 60,
+                note: Some("a, b"),
+            },
+            Record {
+                name: "task2",
+                spent: 0,
+                note: None,
+            },
+        ];
+        let mut buf = Vec::new();
+        write_records(format, &records, &mut buf)?;
+        Ok(String::from_utf8(buf)?)
+    }
+
+    #[test]
+    fn records_as_csv() -> Result<(), BoxError> {
+        assert_eq!(
+            render(OutputFormat::Csv)?,
+            "name,spent,note\ntask1,60,\"a, b\"\ntask2,0,\n"
+        );
+        assert_eq!(
+            render(OutputFormat::Tsv)?,
+            "name\tspent\tnote\ntask1\t60\ta, b\ntask2\t0\t\n"
+        );
+
+        let mut buf = Vec::new();
+        write_records::<Record>(OutputFormat::Csv, &[], &mut buf)?;
+        assert_eq!(String::from_utf8(buf)?, "name,spent,note\n");
+        let mut buf = Vec::new();
+        write_records::<Record>(OutputFormat::Json, &[], &mut buf)?;
+        assert_eq!(String::from_utf8(buf)?, "[]\n");
+        Ok(())
+    }
+
+    #[test]
+    fn records_as_json() -> Result<(), BoxError> {
+        let value: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)?)?;
+        assert_eq!(value[0]["name"], "task1");
+        assert_eq!(value[1]["spent"], 0);
+        assert!(value[1]["note"].is_null());
+        Ok(())
+    }
+}
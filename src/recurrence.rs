@@ -0,0 +1,269 @@
+// recurrence rules of tasks that are renewed on completion
+use crate::error::Error;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::fmt::{self, Display, Formatter};
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Schedule of a recurring task, stored in the `recur` column as text:
+/// `daily`, `weekdays`, `weekly[:mon,thu]` or `monthly[:15]`.
+///
+/// Without days, weekly and monthly rules recur on the weekday or the day of
+/// the month of the due date. Monthly rules are pinned to that day when set, so
+/// a due date moved to the end of a shorter month does not shift later ones.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recurrence {
+    Daily,
+    Weekdays, // Monday to Friday
+    Weekly(Vec<Weekday>),
+    Monthly(Option<u32>),
+}
+
+impl Recurrence {
+    /// First date after `date` the rule recurs on.
+    ///
+    /// Days of the month past the end of a month fall on its last day.
+    pub fn next_after(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => date + Duration::days(1),
+            Recurrence::Weekdays => {
+                let mut next = date + Duration::days(1);
+                while next.weekday() == Weekday::Sat || next.weekday() == Weekday::Sun {
+                    next += Duration::days(1);
+                }
+                next
+            }
+            Recurrence::Weekly(days) if days.is_empty() => date + Duration::weeks(1),
+            Recurrence::Weekly(days) => {
+                let mut next = date + Duration::days(1);
+                while !days.contains(&next.weekday()) {
+                    next += Duration::days(1);
+                }
+                next
+            }
+            Recurrence::Monthly(day) => {
+                let day = day.unwrap_or_else(|| date.day());
+                if day > date.day() && day <= days_in_month(date.year(), date.month()) {
+                    return date.with_day(day).unwrap();
+                }
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                NaiveDate::from_ymd(year, month, day.min(days_in_month(year, month)))
+            }
+        }
+    }
+
+    /// Rule with the day of a bare monthly rule set to the day of `due`.
+    pub fn pinned(&self, due: NaiveDate) -> Recurrence {
+        match self {
+            Recurrence::Monthly(None) => Recurrence::Monthly(Some(due.day())),
+            rule => rule.clone(),
+        }
+    }
+
+    /// First date after `due` the rule recurs on that is not earlier than
+    /// `today`, so occurrences missed while the task stayed open are skipped.
+    pub fn next_due(&self, due: NaiveDate, today: NaiveDate) -> NaiveDate {
+        let mut next = self.next_after(due);
+        while next < today {
+            next = self.next_after(next);
+        }
+        next
+    }
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let first = NaiveDate::from_ymd(year, month, 1);
+    let next = match month {
+        12 => NaiveDate::from_ymd(year + 1, 1, 1),
+        _ => NaiveDate::from_ymd(year, month + 1, 1),
+    };
+    (next - first).num_days() as u32
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "mon",
+        Weekday::Tue => "tue",
+        Weekday::Wed => "wed",
+        Weekday::Thu => "thu",
+        Weekday::Fri => "fri",
+        Weekday::Sat => "sat",
+        Weekday::Sun => "sun",
+    }
+}
+
+impl std::str::FromStr for Recurrence {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidRecurrence {
+            value: s.to_owned(),
+        };
+        let text = s.trim().to_ascii_lowercase();
+        let (kind, args) = match text.find(':') {
+            Some(pos) => (&text[..pos], Some(&text[pos + 1..])),
+            None => (text.as_ref(), None),
+        };
+        match (kind, args) {
+            ("daily", None) => Ok(Recurrence::Daily),
+            ("weekdays", None) => Ok(Recurrence::Weekdays),
+            ("weekly", None) => Ok(Recurrence::Weekly(Vec::new())),
+            ("weekly", Some(args)) => {
+                let mut days = Vec::new();
+                for name in args.split(',') {
+                    let day = name.trim().parse::<Weekday>().map_err(|_| invalid())?;
+                    if !days.contains(&day) {
+                        days.push(day);
+                    }
+                }
+                // keep the canonical form independent of the order given
+                days.sort_by_key(|day| day.num_days_from_monday());
+                Ok(Recurrence::Weekly(days))
+            }
+            ("monthly", None) => Ok(Recurrence::Monthly(None)),
+            ("monthly", Some(day)) => match day.trim().parse::<u32>() {
+                Ok(day) if (1..=31).contains(&day) => Ok(Recurrence::Monthly(Some(day))),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Recurrence {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekdays => write!(f, "weekdays"),
+            Recurrence::Weekly(days) if days.is_empty() => write!(f, "weekly"),
+            Recurrence::Weekly(days) => {
+                let names: Vec<&str> = WEEKDAYS
+                    .iter()
+                    .filter(|day| days.contains(day))
+                    .map(|day| weekday_name(*day))
+                    .collect();
+                write!(f, "weekly:{}", names.join(","))
+            }
+            Recurrence::Monthly(None) => write!(f, "monthly"),
+            Recurrence::Monthly(Some(day)) => write!(f, "monthly:{}", day),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for text in &[
+            "daily",
+            "weekdays",
+            "weekly",
+            "weekly:mon,thu",
+            "monthly",
+            "monthly:15",
+        ] {
+            let rule: Recurrence = text.parse().unwrap();
+            assert_eq!(rule.to_string(), *text);
+        }
+        let rule: Recurrence = "Weekly:Thursday, mon,thu".parse().unwrap();
+        assert_eq!(rule, Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]));
+        assert_eq!(rule.to_string(), "weekly:mon,thu");
+
+        for text in &[
+            "",
+            "hourly",
+            "daily:2",
+            "weekly:someday",
+            "monthly:0",
+            "monthly:32",
+        ] {
+            assert!(text.parse::<Recurrence>().is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn next_dates() {
+        // 2020-06-05 is a Friday
+        let friday = date("2020-06-05");
+        assert_eq!(Recurrence::Daily.next_after(friday), date("2020-06-06"));
+        assert_eq!(Recurrence::Weekdays.next_after(friday), date("2020-06-08"));
+        assert_eq!(
+            Recurrence::Weekly(Vec::new()).next_after(friday),
+            date("2020-06-12")
+        );
+        let rule = Recurrence::Weekly(vec![Weekday::Mon, Weekday::Thu]);
+        assert_eq!(rule.next_after(friday), date("2020-06-08"));
+        assert_eq!(rule.next_after(date("2020-06-08")), date("2020-06-11"));
+
+        assert_eq!(
+            Recurrence::Monthly(None).next_after(friday),
+            date("2020-07-05")
+        );
+        assert_eq!(
+            Recurrence::Monthly(Some(15)).next_after(friday),
+            date("2020-06-15")
+        );
+        assert_eq!(
+            Recurrence::Monthly(Some(31)).next_after(date("2020-01-31")),
+            date("2020-02-29")
+        );
+        assert_eq!(
+            Recurrence::Monthly(Some(31)).next_after(date("2020-02-29")),
+            date("2020-03-31")
+        );
+        assert_eq!(
+            Recurrence::Monthly(Some(5)).next_after(date("2020-12-05")),
+            date("2021-01-05")
+        );
+
+        // a pinned monthly rule returns to its day after February
+        let rule = Recurrence::Monthly(None).pinned(date("2020-01-31"));
+        assert_eq!(rule, Recurrence::Monthly(Some(31)));
+        let mut due = date("2020-01-31");
+        let mut dates = Vec::new();
+        for _ in 0..4 {
+            due = rule.next_after(due);
+            dates.push(due);
+        }
+        assert_eq!(
+            dates,
+            vec![
+                date("2020-02-29"),
+                date("2020-03-31"),
+                date("2020-04-30"),
+                date("2020-05-31")
+            ]
+        );
+        assert_eq!(
+            Recurrence::Daily.pinned(date("2020-01-31")),
+            Recurrence::Daily
+        );
+
+        // missed occurrences are skipped
+        assert_eq!(
+            Recurrence::Weekdays.next_due(friday, date("2020-06-10")),
+            date("2020-06-10")
+        );
+        assert_eq!(
+            Recurrence::Weekly(Vec::new()).next_due(friday, date("2020-06-13")),
+            date("2020-06-19")
+        );
+    }
+}
@@ -1,6 +1,10 @@
 // helper file
-use chrono::{offset::TimeZone, DateTime, Local, NaiveDateTime, Utc};
+use crate::error::Error;
+use chrono::{offset::TimeZone, DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use inflector::Inflector;
+use std::convert::TryFrom;
+
+pub const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 pub type BoxError = Box<dyn std::error::Error + std::marker::Send + std::marker::Sync>;
 
@@ -39,6 +43,17 @@ pub fn fmt_duration(duration: i32, short: bool, zero_text: &str) -> String {
     res.join(" ")
 }
 
+/// Format a duration compactly for shell prompts, e.g. `2h17m` or `45m`.
+pub fn fmt_compact_duration(duration: i32) -> String {
+    let hours = duration / 3600;
+    let minutes = duration % 3600 / 60;
+    if hours > 0 {
+        format!("{}h{:02}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
 pub fn unwrap_string(val: Option<&String>, default: &str) -> String {
     match val {
         Some(txt) => String::from(txt),
@@ -75,24 +90,140 @@ pub fn open_naivedate(data: Option<chrono::NaiveDate>) -> Option<String> {
     }
 }
 
-pub fn utc_to_local_naive(utc: &str) -> Result<String, BoxError> {
-    let naive_dt = NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S")?;
-    let utc_dt: DateTime<Utc> = DateTime::from_utc(naive_dt, Utc);
-    let local_dt = utc_dt.with_timezone(&chrono::offset::Local);
-    Ok(local_dt.format("%Y-%m-%d %H:%M:%S").to_string())
+/// Parse a `%Y-%m-%d %H:%M:%S` timestamp without timezone.
+pub fn parse_timestamp(timestamp: &str) -> Result<NaiveDateTime, Error> {
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).map_err(|_| {
+        Error::InvalidTimestamp {
+            value: timestamp.to_owned(),
+        }
+    })
+}
+
+/// Format a UTC time as `%Y-%m-%d %H:%M:%S` in the local timezone.
+pub fn utc_to_local_naive(utc: &DateTime<Utc>) -> String {
+    utc.with_timezone(&Local)
+        .format(TIMESTAMP_FORMAT)
+        .to_string()
+}
+
+/// Resolve a date and time in the local timezone to UTC.
+pub fn local_to_utc(local: &NaiveDateTime) -> Result<DateTime<Utc>, Error> {
+    let localdt: DateTime<Local> =
+        Local
+            .from_local_datetime(local)
+            .earliest()
+            .ok_or_else(|| Error::InvalidTimestamp {
+                value: local.format(TIMESTAMP_FORMAT).to_string(),
+            })?;
+    Ok(localdt.with_timezone(&Utc))
+}
+
+/// Parse a user supplied local time.
+///
+/// Accepts a full date and time (`2020-05-10 13:45` or `2020-05-10 13:45:30`)
+/// or only a time of day (`13:45`, `13:45:30`), which refers to today.
+pub fn parse_local_time(text: &str) -> Result<NaiveDateTime, Error> {
+    let text = text.trim();
+    for fmt in &[TIMESTAMP_FORMAT, "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(text, fmt) {
+            return Ok(dt);
+        }
+    }
+    let time = NaiveTime::parse_from_str(text, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+        .map_err(|_| Error::InvalidTimestamp {
+            value: text.to_owned(),
+        })?;
+    Ok(Local::today().naive_local().and_time(time))
+}
+
+/// Parse a duration such as `1h30m`, `45m`, `90s` or `1d` into seconds.
+///
+/// A plain number is read as minutes, the same unit used for task allocation.
+pub fn parse_duration(text: &str) -> Result<i32, BoxError> {
+    let text = text.trim();
+    let invalid = || -> BoxError { format!("invalid duration: {}", text).into() };
+    if let Ok(mins) = text.parse::<i32>() {
+        return mins.checked_mul(60).ok_or_else(invalid);
+    }
+    let mut total: i32 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let mul = match c {
+            'd' => 86400,
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        if number.is_empty() {
+            return Err(invalid());
+        }
+        total = number
+            .parse::<i32>()
+            .ok()
+            .and_then(|n| n.checked_mul(mul))
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || text.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Number of seconds from `start` to `stop`.
+pub fn seconds_between(start: &DateTime<Utc>, stop: &DateTime<Utc>) -> Result<i32, Error> {
+    let seconds = stop.signed_duration_since(*start).num_seconds();
+    i32::try_from(seconds).map_err(|_| Error::InvalidTimeRange)
 }
 
-#[allow(dead_code)]
-pub fn local_to_utc(local: &str) -> Result<String, BoxError> {
-    let localnaive = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M:%S")?;
-    let localdt: DateTime<Local> = chrono::Local.from_local_datetime(&localnaive).unwrap();
-    let utcdt = localdt.naive_utc();
-    Ok(utcdt.format("%Y-%m-%d %H:%M:%S").to_string())
+/// Format a UTC time as ISO 8601, e.g. `2020-05-10T08:58:41Z`.
+pub fn utc_to_iso8601(utc: &DateTime<Utc>) -> String {
+    utc.format("%Y-%m-%dT%H:%M:%SZ").to_string()
 }
 
-pub fn get_timestamp() -> String {
-    let nowstamp = Utc::now().naive_local();
-    nowstamp.format("%Y-%m-%d %H:%M:%S").to_string()
+/// UTC time of the local midnight starting given `date`.
+pub fn local_date_to_utc(date: NaiveDate) -> Result<DateTime<Utc>, Error> {
+    local_to_utc(&date.and_hms(0, 0, 0))
+}
+
+/// Split the period from `start` to `end` at each midnight of their timezone.
+///
+/// Returns the seconds spent on every calendar day touched by the period.
+pub fn split_by_day<Tz: TimeZone>(
+    start: &DateTime<Tz>,
+    end: &DateTime<Tz>,
+) -> Vec<(NaiveDate, i32)> {
+    let tz = start.timezone();
+    let mut result = Vec::new();
+    let mut current = start.clone();
+    while current < *end {
+        let date = current.naive_local().date();
+        let midnight = tz
+            .from_local_datetime(&date.succ().and_hms(0, 0, 0))
+            .earliest()
+            .unwrap_or_else(|| end.clone());
+        let next = if midnight < *end {
+            midnight
+        } else {
+            end.clone()
+        };
+        let seconds = next.clone().signed_duration_since(current).num_seconds();
+        result.push((date, seconds as i32));
+        current = next;
+    }
+    result
+}
+
+/// Current time truncated to whole seconds, the precision kept in the database.
+pub fn get_timestamp() -> DateTime<Utc> {
+    Utc.timestamp(Utc::now().timestamp(), 0)
 }
 
 #[test]
@@ -122,3 +253,70 @@ fn formatted_seconds() {
         "17 days 1 hour 12 minutes"
     );
 }
+
+#[test]
+fn compact_durations() {
+    assert_eq!(fmt_compact_duration(0), "0m");
+    assert_eq!(fmt_compact_duration(2700), "45m");
+    assert_eq!(fmt_compact_duration(8228), "2h17m");
+    assert_eq!(fmt_compact_duration(104520), "29h02m");
+}
+
+#[test]
+fn parsed_durations() {
+    assert_eq!(parse_duration("45").unwrap(), 2700);
+    assert_eq!(parse_duration("90s").unwrap(), 90);
+    assert_eq!(parse_duration("20m").unwrap(), 1200);
+    assert_eq!(parse_duration("1h30m").unwrap(), 5400);
+    assert_eq!(parse_duration("1d2h").unwrap(), 93600);
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("h").is_err());
+    assert!(parse_duration("1h30").is_err());
+    assert!(parse_duration("1x").is_err());
+    // out of range of i32 seconds
+    assert!(parse_duration("40000000").is_err());
+    assert!(parse_duration("30000d").is_err());
+    assert!(parse_duration("20000d20000d").is_err());
+    assert!(parse_duration("99999999999s").is_err());
+}
+
+#[test]
+fn parsed_local_times() {
+    let date = NaiveDate::from_ymd(2020, 5, 10);
+    assert_eq!(
+        parse_local_time("2020-05-10 13:45").unwrap(),
+        date.and_hms(13, 45, 0)
+    );
+    assert_eq!(
+        parse_local_time("2020-05-10 13:45:30").unwrap(),
+        date.and_hms(13, 45, 30)
+    );
+    assert_eq!(
+        parse_local_time("13:45").unwrap(),
+        Local::today().naive_local().and_hms(13, 45, 0)
+    );
+    assert!(parse_local_time("yesterday").is_err());
+}
+
+#[test]
+fn split_period_by_day() {
+    let tz = chrono::FixedOffset::east(7 * 3600);
+    let date = NaiveDate::from_ymd(2020, 5, 10);
+
+    let start = tz.ymd(2020, 5, 10).and_hms(9, 0, 0);
+    let end = tz.ymd(2020, 5, 10).and_hms(10, 30, 0);
+    assert_eq!(split_by_day(&start, &end), vec![(date, 5400)]);
+
+    let start = tz.ymd(2020, 5, 10).and_hms(23, 0, 0);
+    let end = tz.ymd(2020, 5, 12).and_hms(1, 0, 0);
+    assert_eq!(
+        split_by_day(&start, &end),
+        vec![
+            (date, 3600),
+            (date.succ(), 86400),
+            (date.succ().succ(), 3600)
+        ]
+    );
+
+    assert!(split_by_day(&end, &start).is_empty());
+}
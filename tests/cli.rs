@@ -137,7 +137,7 @@ fn delete_task() -> Result<(), utils::BoxError> {
         .arg("--noconfirm")
         .assert()
         .success()
-        .stdout(predicate::str::contains("deleted."));
+        .stdout(predicate::str::contains("moved to trash."));
 
     cmd = helper::prepare_cmd(&configpath, &dbpath)?;
     cmd.arg("list")
@@ -149,6 +149,215 @@ fn delete_task() -> Result<(), utils::BoxError> {
     Ok(())
 }
 
+#[test]
+fn trash() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-06-01 09:00",
+        "2020-06-01 10:00",
+    )?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["delete", "task1", "--noconfirm"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["worklog", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No worklog"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "task1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("task1 is in the trash"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["trash", "list", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""taskname": "task1""#))
+        .stdout(predicate::str::contains(r#""spent": 3600"#));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["trash", "restore", "task1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" restored."));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["worklog", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["delete", "task1", "--noconfirm"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["trash", "purge", "--older-than", "1d", "--noconfirm"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing to purge"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["trash", "purge", "--noconfirm"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" deleted."));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["trash", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Trash is empty"));
+
+    Ok(())
+}
+
+#[test]
+fn recur() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "standup", "15", "daily sync")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["recur", "set", "standup", "hourly"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid recurrence rule"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["recur", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No recurring tasks"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["recur", "set", "standup", "weekly:fri,mon"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" recurs weekly:mon,fri."));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["recur", "list", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""taskname": "standup""#))
+        .stdout(predicate::str::contains(r#""rule": "weekly:mon,fri""#));
+
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "standup",
+        "2020-06-05 09:00",
+        "2020-06-05 09:15",
+    )?;
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["edit", "standup", "-d", "2020-06-05", "--finish"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("standup@2020-06-05"))
+        .stdout(predicate::str::contains(" is due again on "));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["list", "-s", "all", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""taskname": "standup@2020-06-05""#,
+        ))
+        .stdout(predicate::str::contains(r#""allocated": 900"#).count(2));
+
+    // finished instances are not subtasks of the renewed task
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["list", "--tree", "-s", "all"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| standup "))
+        .stdout(predicate::str::contains("| standup@2020-06-05 "))
+        .stdout(predicate::str::contains("|   2020-06-05 ").not());
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["list", "-f", "standup", "-s", "all", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("standup@").not());
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["report", "--from", "2020-06-05", "--to", "2020-06-05"])
+        .args(["-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""taskname": "standup@2020-06-05""#,
+        ));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["report", "--from", "2020-06-05", "--to", "2020-06-05"])
+        .args(["-f", "standup", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("standup@").not());
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["recur", "clear", "standup"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" no longer recurs."));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["recur", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No recurring tasks"));
+
+    Ok(())
+}
+
+#[test]
+fn undo_history() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "task1", "task2"]).assert().success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stopall").assert().success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list").assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["history", "-n", "2"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ttrackr stopall"))
+        .stdout(predicate::str::contains("worklog #2 updated ("))
+        .stdout(predicate::str::contains("ttrackr list").not())
+        .stdout(predicate::str::contains("ttrackr create").not());
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["undo", "--noconfirm"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("ttrackr stopall"))
+        .stdout(predicate::str::contains(" undone."));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1"))
+        .stdout(predicate::str::contains("task2"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["undo", "10", "--noconfirm"]).assert().success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["undo", "--noconfirm"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing to undo"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1").not());
+
+    Ok(())
+}
+
 #[test]
 fn delete_invalid_task() -> Result<(), utils::BoxError> {
     let (_tempdir, configpath, dbpath) = utils::setup()?;
@@ -161,6 +370,81 @@ fn delete_invalid_task() -> Result<(), utils::BoxError> {
     Ok(())
 }
 
+#[test]
+fn rename_task() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "proj", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "proj::sub", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "taken", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["rename", "proj", "taken", "--recursive"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("taken already exists"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["rename", "proj", "work", "--recursive"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" renamed to ").count(2));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("work::sub"))
+        .stdout(predicate::str::contains("proj").not());
+
+    Ok(())
+}
+
+#[test]
+fn merge_tasks() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "api::bugfix", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "api::bugfixes", "2", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "api::bugfixes", "--ago", "2h"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "api::bugfix", "--ago", "1h"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["merge", "api::bugfixes", "api::bugfix", "--noconfirm"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("are both running"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args([
+        "merge",
+        "api::bugfixes",
+        "api::bugfix",
+        "--noconfirm",
+        "--force",
+    ])
+    .args(["--allocated", "max"])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(" stopped at "))
+    .stdout(predicate::str::contains(
+        ", 1 worklogs moved (1 cut short or ignored where they overlapped).",
+    ));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["list", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("api::bugfixes").not())
+        .stdout(predicate::str::contains(r#""allocated": 120"#));
+
+    Ok(())
+}
+
 #[test]
 fn start_task() -> Result<(), utils::BoxError> {
     let (_tempdir, configpath, dbpath) = utils::setup()?;
@@ -352,38 +636,1096 @@ fn complete_running_task() -> Result<(), utils::BoxError> {
     Ok(())
 }
 
-mod helper {
-    use super::*;
+#[test]
+fn log_worklog() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
 
-    pub fn prepare_cmd(
-        config: &PathBuf,
-        db: &PathBuf,
-    ) -> Result<std::process::Command, utils::BoxError> {
-        let mut cmd = Command::cargo_bin("ttrackr")?;
-        cmd.arg("--config").arg(&config).arg("--dbfile").arg(&db);
-        Ok(cmd)
-    }
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("log")
+        .arg("task1")
+        .arg("--start")
+        .arg("2020-05-10 09:00")
+        .arg("--end")
+        .arg("2020-05-10 10:30")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("logged 1 hour 30 minutes"));
 
-    pub fn create_task(
-        config: &PathBuf,
-        db: &PathBuf,
-        taskname: &str,
-        alloc: &str,
-        note: &str,
-    ) -> Result<(), utils::BoxError> {
-        let mut cmd = Command::cargo_bin("ttrackr")?;
+    // overlaps previous entry
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("add")
+        .arg("task1")
+        .arg("--start")
+        .arg("2020-05-10 10:00")
+        .arg("--duration")
+        .arg("1h")
+        .assert()
+        .failure();
 
-        // create new task
-        cmd.arg("--config")
-            .arg(config)
-            .arg("--dbfile")
-            .arg(db)
-            .arg("create")
-            .arg(taskname)
-            .arg("-t")
-            .arg(alloc)
-            .arg("-n")
-            .arg(note)
+    // end before start
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("log")
+        .arg("task1")
+        .arg("-s")
+        .arg("2020-05-10 12:00")
+        .arg("-e")
+        .arg("2020-05-10 11:00")
+        .assert()
+        .failure();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("log")
+        .arg("task1")
+        .arg("-s")
+        .arg("2020-05-10 10:30")
+        .arg("-d")
+        .arg("45m")
+        .assert()
+        .success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 hours 15 minutes"));
+
+    Ok(())
+}
+
+#[test]
+fn start_stop_with_timestamp() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .arg("task2")
+        .arg("--ago")
+        .arg("30m")
+        .assert()
+        .success();
+
+    // stop time earlier than start time
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stop")
+        .arg("task1")
+        .arg("--ago")
+        .arg("1h")
+        .assert()
+        .failure();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stop")
+        .arg("task1")
+        .arg("--ago")
+        .arg("10m")
+        .assert()
+        .success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .arg("-f")
+        .arg("task1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("20 minutes"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stopall")
+        .arg("--at")
+        .arg("2000-01-01 00:00")
+        .assert()
+        .failure();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stopall").assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No running task"));
+
+    Ok(())
+}
+
+#[test]
+fn edit_worklogs() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-10 09:00",
+        "2020-05-10 10:00",
+    )?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-10 11:00",
+        "2020-05-10 12:00",
+    )?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1"))
+        .stdout(predicate::str::contains("2020-05-10"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("edit")
+        .arg("1")
+        .arg("--end")
+        .arg("2020-05-10 10:30")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("updated."));
+
+    // overlaps worklog #2
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("edit")
+        .arg("1")
+        .arg("--end")
+        .arg("2020-05-10 11:30")
+        .assert()
+        .failure();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog").arg("ignore").arg("2").assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 hour 30 minutes"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("unignore")
+        .arg("2")
+        .assert()
+        .success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2 hours 30 minutes"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("delete")
+        .arg("2")
+        .arg("--noconfirm")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("deleted."));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("delete")
+        .arg("2")
+        .arg("--noconfirm")
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+#[test]
+fn report() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-10 23:00",
+        "2020-05-11 01:00",
+    )?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task2",
+        "2020-05-11 09:00",
+        "2020-05-11 09:30",
+    )?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("report")
+        .arg("--from")
+        .arg("2020-05-10")
+        .arg("--to")
+        .arg("2020-05-10")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1"))
+        .stdout(predicate::str::contains("1 hour"))
+        .stdout(predicate::str::contains("task2").not());
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("report")
+        .arg("--from")
+        .arg("2020-05-10")
+        .arg("--to")
+        .arg("2020-05-11")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2020-05-11"))
+        .stdout(predicate::str::contains("2 hours 30 minutes"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("report")
+        .arg("--today")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No worklog"));
+
+    Ok(())
+}
+
+#[test]
+fn tags() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("create")
+        .arg("task1")
+        .arg("--tag")
+        .arg("acme")
+        .arg("--tag")
+        .arg("meeting")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("create")
+        .arg("task2")
+        .arg("-t")
+        .arg("30")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("edit")
+        .arg("task2")
+        .arg("--tag")
+        .arg("acme")
+        .assert()
+        .success();
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-10 09:00",
+        "2020-05-10 10:00",
+    )?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task2",
+        "2020-05-10 10:00",
+        "2020-05-10 10:30",
+    )?;
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("tags")
+        .arg("-o")
+        .arg("csv")
+        .assert()
+        .success()
+        .stdout(predicate::str::similar(
+            "tag,tasks,spent,spent_text\nacme,2,5400,1 hour 30 minutes\nmeeting,1,3600,1 hour\n",
+        ));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .arg("--tag")
+        .arg("meeting")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1"))
+        .stdout(predicate::str::contains("acme,meeting"))
+        .stdout(predicate::str::contains("task2").not());
+
+    // editing tags only keeps the allocation
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .arg("--tag")
+        .arg("acme")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task2"))
+        .stdout(predicate::str::contains("30 minutes"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("report")
+        .arg("--from")
+        .arg("2020-05-10")
+        .arg("--tag")
+        .arg("acme")
+        .arg("--tag")
+        .arg("meeting")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1"))
+        .stdout(predicate::str::contains("task2").not());
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("edit")
+        .arg("task1")
+        .arg("--untag")
+        .arg("meeting")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .arg("--tag")
+        .arg("meeting")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Tag meeting not found"));
+
+    Ok(())
+}
+
+#[test]
+fn worklog_notes() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .arg("--ago")
+        .arg("10m")
+        .arg("-m")
+        .arg("reviewed PR 42")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stop")
+        .arg("task1")
+        .arg("--message")
+        .arg("merged")
+        .assert()
+        .success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("reviewed PR 42; merged"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("report")
+        .arg("-o")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""notes": "reviewed PR 42; merged""#,
+        ));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("export")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            r#""notes": "reviewed PR 42; merged""#,
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn pause_resume() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .arg("--ago")
+        .arg("2h")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("pause")
+        .arg("task1")
+        .arg("--ago")
+        .arg("1h")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" paused at "));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("pause")
+        .arg("task1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(" is already paused"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task1 (paused)"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .arg("-o")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""current_spent": 3600"#))
+        .stdout(predicate::str::contains(r#""paused": true"#));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("resume")
+        .arg("task1")
+        .arg("--ago")
+        .arg("30m")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" resumed at "));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("resume")
+        .arg("task1")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(" is not paused"));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stop")
+        .arg("task1")
+        .arg("--ago")
+        .arg("30m")
+        .assert()
+        .success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("list")
+        .arg("-o")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""duration": 3600"#));
+
+    Ok(())
+}
+
+#[test]
+fn export_import_pauses() -> Result<(), utils::BoxError> {
+    let (tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    for (command, at) in &[
+        ("start", "2020-05-12 09:00"),
+        ("pause", "2020-05-12 09:10"),
+        ("resume", "2020-05-12 09:20"),
+        ("stop", "2020-05-12 10:00"),
+    ] {
+        let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+        cmd.args([command, "task1", "--at", at]).assert().success();
+    }
+
+    let archive = tempdir.path().join("archive.json");
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("export").arg(&archive).assert().success();
+    let newdb = tempdir.path().join("new.db");
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("import").arg(&archive).assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.args([
+        "report",
+        "--from",
+        "2020-05-12",
+        "--to",
+        "2020-05-12",
+        "-o",
+        "json",
+    ])
+    .assert()
+    .success()
+    .stdout(predicate::str::contains(r#""spent": 3000"#))
+    .stdout(predicate::str::contains(r#""breaks": 600"#));
+
+    Ok(())
+}
+
+#[test]
+fn switch_task() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task3", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .arg("task2")
+        .arg("--ago")
+        .arg("1h")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("switch")
+        .arg("task3")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" stopped at ").count(2))
+        .stdout(predicate::str::contains(" started at "));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("task3"))
+        .stdout(predicate::str::contains("task1").not());
+
+    // with single_active, start switches too
+    let content = std::fs::read_to_string(&configpath)?;
+    std::fs::write(
+        &configpath,
+        content.replace("single_active = false", "single_active = true"),
+    )?;
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" stopped at "));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task2")
+        .arg("task3")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("use switch").count(2));
+
+    Ok(())
+}
+
+#[test]
+fn max_session() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task3", "1", "")?;
+    let content = std::fs::read_to_string(&configpath)?;
+    std::fs::write(&configpath, format!("max_session = \"1h\"\n{}", content))?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .arg("task2")
+        .arg("--ago")
+        .arg("3h")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "task3", "--at", "2020-05-10 09:00"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("(over max_session)"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stop")
+        .arg("task1")
+        .arg("--truncate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" stopped at "));
+    // not asked without a terminal
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("stop")
+        .arg("task2")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("pass --truncate"));
+    // paused after the threshold, ends when the break started
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["pause", "task3", "--at", "2020-05-10 10:30"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["stop", "task3", "--truncate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(" stopped at "));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("worklog")
+        .arg("list")
+        .arg("-o")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""duration": 3600"#).count(1))
+        .stdout(predicate::str::contains(r#""duration": 5400"#).count(1))
+        .stdout(predicate::str::contains(r#""duration": 108"#).count(1));
+
+    Ok(())
+}
+
+#[test]
+fn stop_batch_with_max_session() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+    let content = std::fs::read_to_string(&configpath)?;
+    std::fs::write(&configpath, format!("max_session = \"1h\"\n{}", content))?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "task1", "--ago", "3h"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["start", "task2", "--ago", "30m"])
+        .assert()
+        .success();
+
+    // task1 ends at max_session, task2 cannot stop before it started
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["stop", "task1", "task2", "--truncate", "--ago", "45m"])
+        .assert()
+        .failure();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["status", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""taskname": "task1""#))
+        .stdout(predicate::str::contains(r#""taskname": "task2""#));
+
+    Ok(())
+}
+
+#[test]
+fn prompt_segment() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+
+    // does not create the config or the database
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("prompt").assert().success().stdout("");
+    assert!(!configpath.exists());
+    assert!(!dbpath.exists());
+
+    helper::create_task(&configpath, &dbpath, "task1", "1", "")?;
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("prompt").assert().success().stdout("");
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start")
+        .arg("task1")
+        .arg("--ago")
+        .arg("1h30m")
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("prompt").assert().success().stdout("task1 1h30m\n");
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("prompt")
+        .arg("--format")
+        .arg("[{count}] {task} {total}")
+        .assert()
+        .success()
+        .stdout("[1] task1 1h30m\n");
+
+    // settings the prompt does not use cannot break it
+    let content = std::fs::read_to_string(&configpath)?;
+    std::fs::write(&configpath, format!("max_session = \"soon\"\n{}", content))?;
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("prompt").assert().success().stdout("task1 1h30m\n");
+
+    Ok(())
+}
+
+#[test]
+fn shell_completions() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "proj::api", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "proj::ui", "1", "")?;
+    helper::create_task(&configpath, &dbpath, "old", "1", "")?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("edit").arg("old").arg("-f").assert().success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("start").arg("proj::ui").assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("completions")
+        .arg("bash")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "complete -F _ttrackr_tasks -o bashdefault -o default ttrackr",
+        ));
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("completions")
+        .arg("zsh")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "':name -- Task name(s):_ttrackr_tasks --running'",
+        ))
+        .stdout(predicate::str::contains("__complete-tasks").count(1));
+
+    let mut cmd = Command::cargo_bin("ttrackr")?;
+    cmd.arg("__complete-tasks")
+        .arg("--config")
+        .arg(&configpath)
+        .assert()
+        .success()
+        .stdout("old\nproj::api\nproj::ui\n");
+    cmd = Command::cargo_bin("ttrackr")?;
+    cmd.arg("__complete-tasks")
+        .arg("--dbfile")
+        .arg(&dbpath)
+        .arg("--incomplete")
+        .assert()
+        .success()
+        .stdout("proj::api\nproj::ui\n");
+    cmd = Command::cargo_bin("ttrackr")?;
+    cmd.arg("__complete-tasks")
+        .arg("--dbfile")
+        .arg(&dbpath)
+        .arg("--running")
+        .assert()
+        .success()
+        .stdout("proj::ui\n");
+
+    Ok(())
+}
+
+#[test]
+fn list_task_tree() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1::sub::a", "60", "")?;
+    helper::create_task(&configpath, &dbpath, "task1::b", "30", "")?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1::sub::a",
+        "2020-05-10 09:00",
+        "2020-05-10 10:00",
+    )?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1::b",
+        "2020-05-10 10:00",
+        "2020-05-10 10:20",
+    )?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .arg("--tree")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| task1 "))
+        .stdout(predicate::str::contains("|   sub "))
+        .stdout(predicate::str::contains("|     a "))
+        .stdout(predicate::str::contains("1 hour 20 minutes"))
+        .stdout(predicate::str::contains("1 hour 30 minutes"));
+
+    Ok(())
+}
+
+#[test]
+fn structured_output() -> Result<(), utils::BoxError> {
+    let (_tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "some, note")?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-10 09:00",
+        "2020-05-10 10:00",
+    )?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .arg("--output")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""taskname": "task1""#))
+        .stdout(predicate::str::contains(r#""spent": 3600"#));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("-o")
+        .arg("csv")
+        .arg("worklog")
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("id,taskname,started,"))
+        .stdout(predicate::str::contains("T09:00:00Z"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("status")
+        .arg("-o")
+        .arg("json")
+        .assert()
+        .success()
+        .stdout(predicate::str::similar("[]\n"));
+
+    Ok(())
+}
+
+#[test]
+fn export_import() -> Result<(), utils::BoxError> {
+    let (tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "task1", "1", "first")?;
+    helper::create_task(&configpath, &dbpath, "task2", "1", "")?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-10 09:00",
+        "2020-05-10 10:00",
+    )?;
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task2",
+        "2020-05-10 10:00",
+        "2020-05-10 10:30",
+    )?;
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["edit", "task1", "--tag", "acme"])
+        .assert()
+        .success();
+
+    let archive = tempdir.path().join("archive.json");
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("export").arg(&archive).assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("export")
+        .arg("--format")
+        .arg("ics")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("BEGIN:VCALENDAR\r\n"))
+        .stdout(predicate::str::contains("SUMMARY:task2\r\n"));
+
+    // into a fresh database
+    let newdb = tempdir.path().join("new.db");
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("import")
+        .arg(&archive)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "2 tasks (0 merged) and 2 worklogs",
+        ));
+
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("first"))
+        .stdout(predicate::str::contains("1 hour"))
+        .stdout(predicate::str::contains("30 minutes"));
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.args(["tags", "-o", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""tag": "acme""#))
+        .stdout(predicate::str::contains(r#""spent": 3600"#));
+
+    // names already exist
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("import").arg(&archive).assert().failure();
+
+    helper::log_worklog(
+        &configpath,
+        &dbpath,
+        "task1",
+        "2020-05-11 09:00",
+        "2020-05-11 09:15",
+    )?;
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("export").arg(&archive).assert().success();
+
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("import")
+        .arg(&archive)
+        .arg("--merge")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "(2 merged) and 1 worklogs (2 skipped)",
+        ));
+
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 hour 15 minutes"));
+
+    // not merged into a task in the trash
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.args(["delete", "task2", "--noconfirm"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &newdb)?;
+    cmd.arg("import")
+        .arg(&archive)
+        .arg("--merge")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("task2 is in the trash"));
+
+    Ok(())
+}
+
+#[test]
+fn import_timewarrior() -> Result<(), utils::BoxError> {
+    let (tempdir, configpath, dbpath) = utils::setup()?;
+    helper::create_task(&configpath, &dbpath, "client::api", "1", "")?;
+    let export = tempdir.path().join("timew.json");
+    std::fs::write(
+        &export,
+        r#"[
+            {"id":3,"start":"20200510T080000Z","end":"20200510T090000Z","tags":["client","api"]},
+            {"id":2,"start":"20200510T090000Z","end":"20200510T093000Z","tags":["client","web"]},
+            {"id":1,"start":"20200510T100000Z","tags":["client","web"]}
+        ]"#,
+    )?;
+
+    let mut cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("import")
+        .arg("--from")
+        .arg("timewarrior")
+        .arg(&export)
+        .arg("--dry-run")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Would create task"))
+        .stdout(predicate::str::contains("client::web"))
+        .stdout(predicate::str::contains(
+            "Would import 2 tasks (1 merged) and 2 worklogs (0 skipped)",
+        ));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("client::web").not());
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("import")
+        .arg("--from")
+        .arg("timewarrior")
+        .arg(&export)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Imported 2 tasks (1 merged) and 2 worklogs (0 skipped)",
+        ));
+
+    // same entries again overlap the imported ones
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("import")
+        .arg("--from")
+        .arg("timewarrior")
+        .arg(&export)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("0 worklogs (2 skipped)"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("client::web"))
+        .stdout(predicate::str::contains("30 minutes"));
+
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["import", "--from", "timewarrior", "--merge"])
+        .arg(&export)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--merge only applies"));
+
+    // entries of a task in the trash are not hidden in it
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["delete", "client::api", "--noconfirm"])
+        .assert()
+        .success();
+    cmd = helper::prepare_cmd(&configpath, &dbpath)?;
+    cmd.args(["import", "--from", "timewarrior"])
+        .arg(&export)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("client::api is in the trash"));
+
+    Ok(())
+}
+
+mod helper {
+    use super::*;
+
+    pub fn prepare_cmd(
+        config: &PathBuf,
+        db: &PathBuf,
+    ) -> Result<std::process::Command, utils::BoxError> {
+        let mut cmd = Command::cargo_bin("ttrackr")?;
+        cmd.arg("--config").arg(&config).arg("--dbfile").arg(&db);
+        Ok(cmd)
+    }
+
+    pub fn create_task(
+        config: &PathBuf,
+        db: &PathBuf,
+        taskname: &str,
+        alloc: &str,
+        note: &str,
+    ) -> Result<(), utils::BoxError> {
+        let mut cmd = Command::cargo_bin("ttrackr")?;
+
+        // create new task
+        cmd.arg("--config")
+            .arg(config)
+            .arg("--dbfile")
+            .arg(db)
+            .arg("create")
+            .arg(taskname)
+            .arg("-t")
+            .arg(alloc)
+            .arg("-n")
+            .arg(note)
+            .assert()
+            .success();
+        Ok(())
+    }
+
+    pub fn log_worklog(
+        config: &PathBuf,
+        db: &PathBuf,
+        taskname: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<(), utils::BoxError> {
+        let mut cmd = prepare_cmd(config, db)?;
+        cmd.arg("log")
+            .arg(taskname)
+            .arg("--start")
+            .arg(start)
+            .arg("--end")
+            .arg(end)
             .assert()
             .success();
         Ok(())